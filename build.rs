@@ -0,0 +1,95 @@
+//! Generates the program id constant. With the `custom-program-id` feature
+//! the id is taken from the `LOOKUP_TABLE_PROGRAM_ID` environment variable,
+//! so forks and test deployments don't have to patch the source.
+//!
+//! Also splits the crate version into the numbers `GetVersion` returns, and
+//! with the `elf` feature resolves the program binary `testing` embeds.
+
+use std::{env, fs, path::Path};
+
+// Shared with the crate, which also formats keys with it.
+#[allow(dead_code)]
+#[path = "src/base58.rs"]
+mod base58;
+
+const CANONICAL_PROGRAM_ID: &str = "AddressLookupTab1e1111111111111111111111111";
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=LOOKUP_TABLE_PROGRAM_ID");
+
+    let program_id = if env::var_os("CARGO_FEATURE_CUSTOM_PROGRAM_ID").is_some() {
+        env::var("LOOKUP_TABLE_PROGRAM_ID").unwrap_or_else(|_| CANONICAL_PROGRAM_ID.to_string())
+    } else {
+        CANONICAL_PROGRAM_ID.to_string()
+    };
+
+    // Decoded here rather than with `pinocchio_pubkey::from_str`, so the
+    // constant is available without the `program` feature.
+    let bytes = base58::decode_32(&program_id).unwrap_or_else(|| {
+        panic!("LOOKUP_TABLE_PROGRAM_ID is not a base58 encoded address: {program_id}")
+    });
+    let canonical = base58::decode_32(CANONICAL_PROGRAM_ID).unwrap();
+    let source = format!(
+        "/// Address of the Address Lookup Table program this crate is built for.\n\
+         pub const LOOKUP_TABLE_PROGRAM_ID: Pubkey = {bytes:?};\n\
+         /// Address of the native Address Lookup Table program.\n\
+         pub const CANONICAL_LOOKUP_TABLE_PROGRAM_ID: Pubkey = {canonical:?};\n"
+    );
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("program_id.rs"), source).unwrap();
+
+    let version = env::var("CARGO_PKG_VERSION").unwrap();
+    let (major, minor, patch) = parse_version(&version)
+        .unwrap_or_else(|| panic!("crate version {version} does not fit in u8.u8.u16"));
+    let source = format!(
+        "pub const PROGRAM_VERSION_MAJOR: u8 = {major};\n\
+         pub const PROGRAM_VERSION_MINOR: u8 = {minor};\n\
+         pub const PROGRAM_VERSION_PATCH: u16 = {patch};\n"
+    );
+    fs::write(Path::new(&out_dir).join("version.rs"), source).unwrap();
+
+    if env::var_os("CARGO_FEATURE_ELF").is_some() {
+        embed_program_elf();
+    }
+}
+
+/// Points `testing::program_elf` at the binary to embed. Built `.so` files
+/// are not checked in, so a crate depending on this one from git or
+/// crates.io sets `LOOKUP_TABLE_PROGRAM_ELF` to its own `cargo build-sbf`
+/// output; inside this repository it defaults to `tests/fixtures`.
+fn embed_program_elf() {
+    println!("cargo:rerun-if-env-changed=LOOKUP_TABLE_PROGRAM_ELF");
+
+    let path = match env::var_os("LOOKUP_TABLE_PROGRAM_ELF") {
+        Some(path) => Path::new(&path).to_path_buf(),
+        None => Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap())
+            .join("tests/fixtures/p_address_lookup_table.so"),
+    };
+    let path = fs::canonicalize(&path).unwrap_or_else(|_| {
+        panic!(
+            "the `elf` feature embeds the program binary, but {} does not exist: \
+             build it with `cargo build-sbf` and set LOOKUP_TABLE_PROGRAM_ELF to its path",
+            path.display()
+        )
+    });
+
+    println!("cargo:rerun-if-changed={}", path.display());
+    println!(
+        "cargo:rustc-env=LOOKUP_TABLE_PROGRAM_ELF={}",
+        path.display()
+    );
+}
+
+/// Splits `major.minor.patch`, ignoring any pre-release or build suffix.
+fn parse_version(version: &str) -> Option<(u8, u8, u16)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.');
+    let version = (
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+    );
+    parts.next().is_none().then_some(version)
+}
@@ -0,0 +1,161 @@
+//! Anchor CPI wrappers for programs that create and extend lookup tables
+//! they control. The account structs follow the `ToAccountMetas` and
+//! `ToAccountInfos` patterns of Anchor's generated CPI clients, with the
+//! account order and access flags taken from [`crate::instruction`].
+//!
+//! ```no_run
+//! use anchor_lang::prelude::*;
+//! use p_address_lookup_table::anchor::{cpi, CreateLookupTable};
+//!
+//! fn create_table<'info>(
+//!     lookup_table_program: AccountInfo<'info>,
+//!     accounts: CreateLookupTable<'info>,
+//!     recent_slot: u64,
+//!     bump_seed: u8,
+//! ) -> Result<()> {
+//!     let ctx = CpiContext::new(lookup_table_program, accounts);
+//!     cpi::create_lookup_table(ctx, recent_slot, bump_seed)
+//! }
+//! ```
+
+use alloc::vec::Vec;
+
+use anchor_lang::solana_program::{
+    account_info::AccountInfo, instruction::AccountMeta, pubkey::Pubkey,
+};
+use anchor_lang::{ToAccountInfos, ToAccountMetas};
+
+use crate::instruction::{
+    AccountAccess, CREATE_LOOKUP_TABLE_ACCOUNTS, EXTEND_LOOKUP_TABLE_ACCOUNTS,
+};
+
+fn account_metas(
+    keys: [&Pubkey; 4],
+    accesses: &[AccountAccess; 4],
+    is_signer: Option<bool>,
+) -> Vec<AccountMeta> {
+    keys.iter()
+        .zip(accesses)
+        .map(|(key, access)| AccountMeta {
+            pubkey: **key,
+            is_signer: access.is_signer || is_signer.unwrap_or(false),
+            is_writable: access.is_writable,
+        })
+        .collect()
+}
+
+pub struct CreateLookupTable<'info> {
+    pub lookup_table: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+impl ToAccountMetas for CreateLookupTable<'_> {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        account_metas(
+            [
+                self.lookup_table.key,
+                self.authority.key,
+                self.payer.key,
+                self.system_program.key,
+            ],
+            &CREATE_LOOKUP_TABLE_ACCOUNTS,
+            is_signer,
+        )
+    }
+}
+
+impl<'info> ToAccountInfos<'info> for CreateLookupTable<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        alloc::vec![
+            self.lookup_table.clone(),
+            self.authority.clone(),
+            self.payer.clone(),
+            self.system_program.clone(),
+        ]
+    }
+}
+
+pub struct ExtendLookupTable<'info> {
+    pub lookup_table: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+impl ToAccountMetas for ExtendLookupTable<'_> {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        account_metas(
+            [
+                self.lookup_table.key,
+                self.authority.key,
+                self.payer.key,
+                self.system_program.key,
+            ],
+            &EXTEND_LOOKUP_TABLE_ACCOUNTS,
+            is_signer,
+        )
+    }
+}
+
+impl<'info> ToAccountInfos<'info> for ExtendLookupTable<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        alloc::vec![
+            self.lookup_table.clone(),
+            self.authority.clone(),
+            self.payer.clone(),
+            self.system_program.clone(),
+        ]
+    }
+}
+
+pub mod cpi {
+    use alloc::vec::Vec;
+
+    use anchor_lang::context::CpiContext;
+    use anchor_lang::solana_program::{
+        instruction::Instruction, program::invoke_signed, program_error::ProgramError,
+        pubkey::Pubkey,
+    };
+    use anchor_lang::{Result, ToAccountInfos, ToAccountMetas};
+
+    use super::{CreateLookupTable, ExtendLookupTable};
+    use crate::instruction::{create_lookup_table_data, ExtendInstructionBuilder};
+
+    pub fn create_lookup_table<'info>(
+        ctx: CpiContext<'_, '_, '_, 'info, CreateLookupTable<'info>>,
+        recent_slot: u64,
+        bump_seed: u8,
+    ) -> Result<()> {
+        invoke(ctx, create_lookup_table_data(recent_slot, bump_seed))
+    }
+
+    pub fn extend_lookup_table<'info>(
+        ctx: CpiContext<'_, '_, '_, 'info, ExtendLookupTable<'info>>,
+        new_addresses: &[Pubkey],
+    ) -> Result<()> {
+        let mut builder = ExtendInstructionBuilder::default();
+        for address in new_addresses {
+            builder
+                .push(address.to_bytes())
+                .map_err(|_| ProgramError::InvalidArgument)?;
+        }
+        invoke(ctx, builder.build())
+    }
+
+    fn invoke<'info, T: ToAccountMetas + ToAccountInfos<'info>>(
+        ctx: CpiContext<'_, '_, '_, 'info, T>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let instruction = Instruction {
+            program_id: *ctx.program.key,
+            accounts: ctx.accounts.to_account_metas(None),
+            data,
+        };
+        let mut account_infos = ctx.accounts.to_account_infos();
+        account_infos.push(ctx.program.clone());
+
+        invoke_signed(&instruction, &account_infos, ctx.signer_seeds).map_err(Into::into)
+    }
+}
@@ -0,0 +1,63 @@
+//! Base58 for 32-byte keys, shared by the `Debug` output, the RPC helpers
+//! and `build.rs`, which includes this file to decode the program id.
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Formats bytes, e.g. a key, as base58.
+pub(crate) struct Base58<'a>(pub(crate) &'a [u8]);
+
+impl core::fmt::Debug for Base58<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // A 32-byte key never needs more than 44 base58 digits.
+        debug_assert!(self.0.len() <= 32);
+        let mut digits = [0u8; 44];
+        let mut len = 0;
+
+        for byte in self.0 {
+            let mut carry = *byte as u32;
+            for digit in digits[..len].iter_mut() {
+                carry += (*digit as u32) << 8;
+                *digit = (carry % 58) as u8;
+                carry /= 58;
+            }
+            while carry > 0 {
+                digits[len] = (carry % 58) as u8;
+                len += 1;
+                carry /= 58;
+            }
+        }
+
+        for _ in self.0.iter().take_while(|byte| **byte == 0) {
+            f.write_str("1")?;
+        }
+        for digit in digits[..len].iter().rev() {
+            write!(f, "{}", ALPHABET[*digit as usize] as char)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes a base58 string holding exactly 32 bytes.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) fn decode_32(value: &str) -> Option<[u8; 32]> {
+    // Big endian, least significant byte last.
+    let mut bytes = [0u8; 32];
+    for character in value.bytes() {
+        let mut carry = ALPHABET.iter().position(|c| *c == character)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+
+    // Each leading '1' stands for a leading zero byte; a string with fewer
+    // of them decodes to a shorter value than a 32-byte address.
+    let leading_ones = value.bytes().take_while(|c| *c == b'1').count();
+    let leading_zeros = bytes.iter().take_while(|b| **b == 0).count();
+    (leading_ones == leading_zeros).then_some(bytes)
+}
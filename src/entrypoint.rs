@@ -1,10 +1,11 @@
 use pinocchio::{
     account_info::AccountInfo, no_allocator, nostd_panic_handler, program_entrypoint,
-    program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+    pubkey::Pubkey, ProgramResult,
 };
 
 use pinocchio_log::log;
 
+use crate::instruction::ProgramInstruction;
 use crate::processor;
 
 program_entrypoint!(process_instruction);
@@ -16,62 +17,93 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    let discriminator = u32::from_le_bytes(
-        instruction_data[0..4]
-            .try_into()
-            .map_err(|_| ProgramError::InvalidInstructionData)?,
-    );
-
-    match discriminator {
-        0 => {
+    match ProgramInstruction::unpack(instruction_data)? {
+        ProgramInstruction::CreateLookupTable {
+            recent_slot,
+            bump_seed,
+        } => {
             log!("Instruction: CreateLookupTable");
-            let untrusted_recent_slot = u64::from_le_bytes(
-                instruction_data[4..12]
-                    .try_into()
-                    .map_err(|_| ProgramError::InvalidInstructionData)?,
-            );
-
-            let bump_seed = instruction_data[12];
-            processor::process_create_lookup_table(
-                program_id,
-                accounts,
-                untrusted_recent_slot,
-                bump_seed,
-            )?
+            processor::process_create_lookup_table(program_id, accounts, recent_slot, bump_seed)
         }
-        1 => {
+        ProgramInstruction::FreezeLookupTable => {
             log!("Instruction: FreezeLookupTable");
-            processor::process_freeze_lookup_table(program_id, accounts)?
+            processor::process_freeze_lookup_table(program_id, accounts)
         }
-        2 => {
+        ProgramInstruction::ExtendLookupTable { addresses } => {
             log!("Instruction: ExtendLookupTable");
-            let address_len = u64::from_le_bytes(
-                instruction_data[4..12]
-                    .try_into()
-                    .map_err(|_| ProgramError::InvalidInstructionData)?,
-            ) as usize;
-
-            let addresses_start = 12;
-            let addresses_end = addresses_start + address_len * 32;
-
-            if instruction_data.len() != addresses_end {
-                return Err(ProgramError::InvalidInstructionData);
-            }
-
-            let raw_addresses = &instruction_data[addresses_start..addresses_end];
-
-            processor::process_extend_lookup_table(program_id, accounts, raw_addresses)?
+            processor::process_extend_lookup_table(program_id, accounts, addresses, None, false)
         }
-        3 => {
+        ProgramInstruction::DeactivateLookupTable { reason } => {
             log!("Instruction: DeactivateLookupTable");
-            processor::process_deactivate_lookup_table(program_id, accounts)?
+            processor::process_deactivate_lookup_table(program_id, accounts, reason)
         }
-        4 => {
+        ProgramInstruction::CloseLookupTable { flags } => {
             log!("Instruction: CloseLookupTable");
-            processor::process_close_lookup_table(program_id, accounts)?
+            processor::process_close_lookup_table(program_id, accounts, flags)
+        }
+        ProgramInstruction::CollectAddresses => {
+            log!("Instruction: CollectAddresses");
+            processor::process_collect_addresses(program_id, accounts)
+        }
+        ProgramInstruction::CreateLookupTableFromClock => {
+            log!("Instruction: CreateLookupTableFromClock");
+            processor::process_create_lookup_table_from_clock(program_id, accounts)
+        }
+        ProgramInstruction::ExtendLookupTableChecked {
+            expected_version,
+            addresses,
+        } => {
+            log!("Instruction: ExtendLookupTableChecked");
+            processor::process_extend_lookup_table(
+                program_id,
+                accounts,
+                addresses,
+                Some(expected_version),
+                false,
+            )
+        }
+        ProgramInstruction::TransferAuthority => {
+            log!("Instruction: TransferAuthority");
+            processor::process_transfer_authority(program_id, accounts)
+        }
+        ProgramInstruction::TopUpRent => {
+            log!("Instruction: TopUpRent");
+            processor::process_top_up_rent(program_id, accounts)
+        }
+        ProgramInstruction::AddressesReady => {
+            log!("Instruction: AddressesReady");
+            processor::process_addresses_ready(program_id, accounts)
+        }
+        ProgramInstruction::ReactivateLookupTable => {
+            log!("Instruction: ReactivateLookupTable");
+            processor::process_reactivate_lookup_table(program_id, accounts)
+        }
+        ProgramInstruction::ExtendLookupTableWithAllowlist { addresses } => {
+            log!("Instruction: ExtendLookupTableWithAllowlist");
+            processor::process_extend_lookup_table(program_id, accounts, addresses, None, true)
+        }
+        ProgramInstruction::RentStatus => {
+            log!("Instruction: RentStatus");
+            processor::process_rent_status(program_id, accounts)
+        }
+        ProgramInstruction::GetAge => {
+            log!("Instruction: GetAge");
+            processor::process_get_age(program_id, accounts)
+        }
+        ProgramInstruction::GetVersion => {
+            log!("Instruction: GetVersion");
+            processor::process_get_version()
+        }
+        ProgramInstruction::ExtendAllowlist {
+            bump_seed,
+            addresses,
+        } => {
+            log!("Instruction: ExtendAllowlist");
+            processor::process_extend_allowlist(program_id, accounts, bump_seed, addresses)
+        }
+        ProgramInstruction::ClearAllowlist => {
+            log!("Instruction: ClearAllowlist");
+            processor::process_clear_allowlist(program_id, accounts)
         }
-        _ => return Err(ProgramError::InvalidInstructionData),
     }
-
-    Ok(())
 }
@@ -0,0 +1,19 @@
+use pinocchio::program_error::ProgramError;
+
+pub use crate::interface::LookupTableError;
+use crate::state::StateError;
+
+impl From<LookupTableError> for ProgramError {
+    fn from(error: LookupTableError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+impl From<StateError> for ProgramError {
+    fn from(error: StateError) -> Self {
+        match error {
+            StateError::InvalidArgument => ProgramError::InvalidArgument,
+            StateError::InvalidAccountData => ProgramError::InvalidAccountData,
+        }
+    }
+}
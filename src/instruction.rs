@@ -0,0 +1,450 @@
+#[cfg(feature = "client")]
+use alloc::vec::Vec;
+
+pub use crate::interface::{
+    CLOSE_BURN_LAMPORTS, CLOSE_FLAGS, CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT,
+};
+use pinocchio::{program_error::ProgramError, sysvars::clock::Slot};
+use pinocchio_log::log;
+
+#[cfg(feature = "client")]
+use pinocchio::{
+    pubkey::{Pubkey, PUBKEY_BYTES},
+    sysvars::rent::Rent,
+};
+
+#[cfg(feature = "client")]
+use crate::interface::{lookup_table_seeds, LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE};
+use crate::interface::{
+    LookupTableError, ADDRESSES_READY, CLEAR_ALLOWLIST, CLOSE_LOOKUP_TABLE, COLLECT_ADDRESSES,
+    CREATE_LOOKUP_TABLE, CREATE_LOOKUP_TABLE_FROM_CLOCK, DEACTIVATE_LOOKUP_TABLE, EXTEND_ALLOWLIST,
+    EXTEND_LOOKUP_TABLE, EXTEND_LOOKUP_TABLE_CHECKED, EXTEND_LOOKUP_TABLE_WITH_ALLOWLIST,
+    FREEZE_LOOKUP_TABLE, GET_AGE, GET_VERSION, REACTIVATE_LOOKUP_TABLE, RENT_STATUS, TOP_UP_RENT,
+    TRANSFER_AUTHORITY,
+};
+
+/// An instruction decoded from its data, borrowing the addresses of the
+/// extend variants. The entrypoint dispatches on this, and programs
+/// inspecting other instructions of a transaction decode them the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgramInstruction<'a> {
+    CreateLookupTable {
+        recent_slot: Slot,
+        bump_seed: u8,
+    },
+    FreezeLookupTable,
+    /// `addresses` holds the new addresses packed back to back.
+    ExtendLookupTable {
+        addresses: &'a [u8],
+    },
+    DeactivateLookupTable {
+        reason: u8,
+    },
+    CloseLookupTable {
+        flags: u8,
+    },
+    CollectAddresses,
+    CreateLookupTableFromClock,
+    ExtendLookupTableChecked {
+        expected_version: u8,
+        addresses: &'a [u8],
+    },
+    TransferAuthority,
+    TopUpRent,
+    AddressesReady,
+    ReactivateLookupTable,
+    ExtendLookupTableWithAllowlist {
+        addresses: &'a [u8],
+    },
+    RentStatus,
+    GetAge,
+    GetVersion,
+    /// `addresses` must be sorted in ascending byte order and follow the
+    /// addresses already in the allowlist.
+    ExtendAllowlist {
+        bump_seed: u8,
+        addresses: &'a [u8],
+    },
+    ClearAllowlist,
+}
+
+impl<'a> ProgramInstruction<'a> {
+    /// Decodes instruction data. Bytes after the fields an instruction reads
+    /// are ignored, as the native program does, except for the extend
+    /// variants whose address list must end the data.
+    pub fn unpack(data: &'a [u8]) -> Result<Self, ProgramError> {
+        let (discriminator, payload) = data
+            .split_first_chunk::<4>()
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        let discriminator = u32::from_le_bytes(*discriminator);
+
+        Ok(match discriminator {
+            CREATE_LOOKUP_TABLE => {
+                let (recent_slot, payload) = payload
+                    .split_first_chunk::<8>()
+                    .ok_or(ProgramError::InvalidInstructionData)?;
+                let bump_seed = *payload
+                    .first()
+                    .ok_or(ProgramError::InvalidInstructionData)?;
+                Self::CreateLookupTable {
+                    recent_slot: Slot::from_le_bytes(*recent_slot),
+                    bump_seed,
+                }
+            }
+            FREEZE_LOOKUP_TABLE => Self::FreezeLookupTable,
+            EXTEND_LOOKUP_TABLE => Self::ExtendLookupTable {
+                addresses: parse_new_addresses(payload)?,
+            },
+            // The reason byte is optional so the 4-byte payload of the
+            // native instruction keeps working.
+            DEACTIVATE_LOOKUP_TABLE => Self::DeactivateLookupTable {
+                reason: payload.first().copied().unwrap_or_default(),
+            },
+            CLOSE_LOOKUP_TABLE => {
+                let flags = payload.first().copied().unwrap_or_default();
+                if flags & !CLOSE_FLAGS != 0 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Self::CloseLookupTable { flags }
+            }
+            COLLECT_ADDRESSES => Self::CollectAddresses,
+            CREATE_LOOKUP_TABLE_FROM_CLOCK => Self::CreateLookupTableFromClock,
+            EXTEND_LOOKUP_TABLE_CHECKED => {
+                let [expected_version, rest @ ..] = payload else {
+                    return Err(ProgramError::InvalidInstructionData);
+                };
+                Self::ExtendLookupTableChecked {
+                    expected_version: *expected_version,
+                    addresses: parse_new_addresses(rest)?,
+                }
+            }
+            TRANSFER_AUTHORITY => Self::TransferAuthority,
+            TOP_UP_RENT => Self::TopUpRent,
+            ADDRESSES_READY => Self::AddressesReady,
+            REACTIVATE_LOOKUP_TABLE => Self::ReactivateLookupTable,
+            EXTEND_LOOKUP_TABLE_WITH_ALLOWLIST => Self::ExtendLookupTableWithAllowlist {
+                addresses: parse_new_addresses(payload)?,
+            },
+            RENT_STATUS => Self::RentStatus,
+            GET_AGE => Self::GetAge,
+            GET_VERSION => Self::GetVersion,
+            EXTEND_ALLOWLIST => {
+                let [bump_seed, rest @ ..] = payload else {
+                    return Err(ProgramError::InvalidInstructionData);
+                };
+                Self::ExtendAllowlist {
+                    bump_seed: *bump_seed,
+                    addresses: parse_new_addresses(rest)?,
+                }
+            }
+            CLEAR_ALLOWLIST => Self::ClearAllowlist,
+            _ => {
+                log!("Unknown instruction discriminator {}", discriminator);
+                return Err(LookupTableError::UnknownInstruction.into());
+            }
+        })
+    }
+}
+
+/// Parses the bincode encoded `Vec<Pubkey>` of an extend instruction: a `u64`
+/// length followed by exactly that many addresses.
+fn parse_new_addresses(data: &[u8]) -> Result<&[u8], ProgramError> {
+    let address_len = u64::from_le_bytes(
+        data.get(0..8)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(ProgramError::InvalidInstructionData)?,
+    ) as usize;
+
+    let addresses_start = 8;
+    let addresses_end = address_len
+        .checked_mul(32)
+        .and_then(|len| len.checked_add(addresses_start))
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    if data.len() != addresses_end {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(&data[addresses_start..addresses_end])
+}
+
+/// Whether an instruction account must be writable and whether it signs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccountAccess {
+    pub is_writable: bool,
+    pub is_signer: bool,
+}
+
+const fn access(is_writable: bool, is_signer: bool) -> AccountAccess {
+    AccountAccess {
+        is_writable,
+        is_signer,
+    }
+}
+
+/// `CreateLookupTable` accounts: the table, its authority, the payer and the
+/// system program. The authority does not need to sign.
+pub const CREATE_LOOKUP_TABLE_ACCOUNTS: [AccountAccess; 4] = [
+    access(true, false),
+    access(false, false),
+    access(true, true),
+    access(false, false),
+];
+
+/// `ExtendLookupTable` accounts: the table, its authority, the payer and the
+/// system program.
+pub const EXTEND_LOOKUP_TABLE_ACCOUNTS: [AccountAccess; 4] = [
+    access(true, false),
+    access(false, true),
+    access(true, true),
+    access(false, false),
+];
+
+/// `CloseLookupTable` accounts: the table, its authority and the recipient
+/// of the reclaimed lamports. An authority reclaiming to itself is passed in
+/// both roles and must then be writable as well as a signer.
+pub const CLOSE_LOOKUP_TABLE_ACCOUNTS: [AccountAccess; 3] = [
+    access(true, false),
+    access(false, true),
+    access(true, false),
+];
+
+/// Owned instruction data of the native program's instructions, for clients
+/// building or reading transactions. This program's own instructions and the
+/// optional deactivation reason and close flags are not represented.
+#[cfg(feature = "client")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstructionData {
+    Create { recent_slot: Slot, bump: u8 },
+    Freeze,
+    Extend { addresses: Vec<[u8; 32]> },
+    Deactivate,
+    Close,
+}
+
+#[cfg(feature = "client")]
+impl InstructionData {
+    pub fn serialize(&self) -> Vec<u8> {
+        match self {
+            Self::Create { recent_slot, bump } => create_lookup_table_data(*recent_slot, *bump),
+            Self::Freeze => FREEZE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+            // Filled directly rather than through `push`, so that addresses
+            // beyond the table capacity are encoded as given, not dropped.
+            Self::Extend { addresses } => ExtendInstructionBuilder {
+                addresses: addresses.clone(),
+            }
+            .build(),
+            Self::Deactivate => DEACTIVATE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+            Self::Close => CLOSE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+/// Decodes with [`ProgramInstruction::unpack`]. Data the enum can't hold,
+/// i.e. another instruction, a deactivation reason or close flags, is
+/// refused rather than silently dropped.
+#[cfg(feature = "client")]
+impl TryFrom<&[u8]> for InstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        Ok(match ProgramInstruction::unpack(data)? {
+            ProgramInstruction::CreateLookupTable {
+                recent_slot,
+                bump_seed,
+            } => Self::Create {
+                recent_slot,
+                bump: bump_seed,
+            },
+            ProgramInstruction::FreezeLookupTable => Self::Freeze,
+            ProgramInstruction::ExtendLookupTable { addresses } => Self::Extend {
+                addresses: addresses
+                    .chunks_exact(PUBKEY_BYTES)
+                    .filter_map(|address| address.try_into().ok())
+                    .collect(),
+            },
+            ProgramInstruction::DeactivateLookupTable { reason: 0 } => Self::Deactivate,
+            ProgramInstruction::CloseLookupTable { flags: 0 } => Self::Close,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        })
+    }
+}
+
+/// `CreateLookupTable` instruction data.
+#[cfg(feature = "client")]
+pub fn create_lookup_table_data(recent_slot: Slot, bump_seed: u8) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + 8 + 1);
+    data.extend_from_slice(&CREATE_LOOKUP_TABLE.to_le_bytes());
+    data.extend_from_slice(&recent_slot.to_le_bytes());
+    data.push(bump_seed);
+    data
+}
+
+/// Derives the address of the lookup table created by `authority` at
+/// `recent_slot` with `bump_seed`, using the same seeds as the program.
+///
+/// Like [`pinocchio_pubkey::derive_address_const`], this does not check that
+/// the result is off-curve, so `bump_seed` should come from a canonical
+/// `find_program_address` search.
+#[cfg(feature = "client")]
+pub fn derive_lookup_table_address(
+    authority: &Pubkey,
+    recent_slot: Slot,
+    bump_seed: u8,
+    program_id: &Pubkey,
+) -> Pubkey {
+    pinocchio_pubkey::derive_address_const(
+        &lookup_table_seeds(authority, &recent_slot.to_le_bytes(), &[bump_seed]),
+        None,
+        program_id,
+    )
+}
+
+/// Assembles `ExtendLookupTable` instruction data, refusing more addresses
+/// than a lookup table can hold.
+#[cfg(feature = "client")]
+#[derive(Default)]
+pub struct ExtendInstructionBuilder {
+    addresses: Vec<[u8; 32]>,
+}
+
+#[cfg(feature = "client")]
+impl ExtendInstructionBuilder {
+    pub fn push(&mut self, address: Pubkey) -> Result<&mut Self, ProgramError> {
+        if self.addresses.len() >= LOOKUP_TABLE_MAX_ADDRESSES {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        self.addresses.push(address);
+        Ok(self)
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(4 + 8 + self.addresses.len() * 32);
+        data.extend_from_slice(&EXTEND_LOOKUP_TABLE.to_le_bytes());
+        data.extend_from_slice(&(self.addresses.len() as u64).to_le_bytes());
+        for address in &self.addresses {
+            data.extend_from_slice(address);
+        }
+        data
+    }
+}
+
+/// Largest number of addresses a table holding `current_count` addresses
+/// can be extended with when the payer has `payer_lamports` to cover the
+/// extra rent, capped by the table capacity. Assumes the table is exactly
+/// rent exempt for its current size.
+#[cfg(feature = "client")]
+pub fn max_addable(current_count: usize, payer_lamports: u64, rent: &Rent) -> usize {
+    let data_len = |count: usize| LOOKUP_TABLE_META_SIZE + count * PUBKEY_BYTES;
+    let funded_lamports = rent.minimum_balance(data_len(current_count));
+
+    (0..=LOOKUP_TABLE_MAX_ADDRESSES.saturating_sub(current_count))
+        .rev()
+        .find(|added| {
+            rent.minimum_balance(data_len(current_count + added))
+                .saturating_sub(funded_lamports)
+                <= payer_lamports
+        })
+        .unwrap_or_default()
+}
+
+/// Transaction size limit, the payload of a network packet.
+#[cfg(feature = "client")]
+const PACKET_DATA_SIZE: usize = 1232;
+
+/// A transaction holding `CreateLookupTable` and an `ExtendLookupTable` with
+/// no addresses, signed by an authority and a payer that are distinct
+/// accounts.
+#[cfg(feature = "client")]
+const CREATE_AND_EXTEND_TRANSACTION_SIZE: usize = (1 + 2 * 64) // signatures
+    + 3 // message header
+    + (1 + 5 * PUBKEY_BYTES) // table, authority, payer, system program, this program
+    + 32 // recent blockhash
+    + 1 // instruction count
+    + (1 + 1 + 4 + 1 + 4 + 8 + 1) // create: program, accounts, data
+    + (1 + 1 + 4 + 2 + 4 + 8); // extend, before its addresses
+
+/// Addresses a planned `ExtendLookupTable` carries, so that the first one
+/// fits in a transaction next to `CreateLookupTable`. Later extends, alone
+/// in their transaction, use the same size.
+#[cfg(feature = "client")]
+pub const MAX_ADDRESSES_PER_EXTEND: usize =
+    (PACKET_DATA_SIZE - CREATE_AND_EXTEND_TRANSACTION_SIZE) / PUBKEY_BYTES;
+
+/// An instruction to this program, owned so a plan can be kept around and
+/// converted into whichever SDK's instruction type the client uses.
+#[cfg(feature = "client")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlannedInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<(Pubkey, AccountAccess)>,
+    pub data: Vec<u8>,
+}
+
+/// Everything needed to create a table holding a list of addresses.
+#[cfg(feature = "client")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TablePlan {
+    pub table_address: Pubkey,
+    /// `CreateLookupTable` followed by the extends, in order.
+    pub instructions: Vec<PlannedInstruction>,
+    /// Rent of the final table, which the payer funds across the
+    /// instructions.
+    pub estimated_rent_lamports: u64,
+    /// Create shares a transaction with the first extend, and every further
+    /// extend takes one of its own.
+    pub transactions_needed: usize,
+}
+
+/// Plans the creation of a table for `authority`, paid by `payer`, holding
+/// exactly `addresses` in order. As with [`derive_lookup_table_address`],
+/// `bump_seed` must be the canonical bump for `authority` and `recent_slot`.
+#[cfg(feature = "client")]
+pub fn plan_table_creation(
+    addresses: &[Pubkey],
+    authority: &Pubkey,
+    payer: &Pubkey,
+    recent_slot: Slot,
+    bump_seed: u8,
+    rent: &Rent,
+) -> Result<TablePlan, ProgramError> {
+    if addresses.len() > LOOKUP_TABLE_MAX_ADDRESSES {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let program_id = crate::LOOKUP_TABLE_PROGRAM_ID;
+    let table_address = derive_lookup_table_address(authority, recent_slot, bump_seed, &program_id);
+    let accounts = |access: &[AccountAccess]| -> Vec<(Pubkey, AccountAccess)> {
+        [table_address, *authority, *payer, pinocchio_system::ID]
+            .into_iter()
+            .zip(access.iter().copied())
+            .collect()
+    };
+
+    let mut instructions =
+        Vec::with_capacity(1 + addresses.len().div_ceil(MAX_ADDRESSES_PER_EXTEND));
+    instructions.push(PlannedInstruction {
+        program_id,
+        accounts: accounts(&CREATE_LOOKUP_TABLE_ACCOUNTS),
+        data: create_lookup_table_data(recent_slot, bump_seed),
+    });
+    for chunk in addresses.chunks(MAX_ADDRESSES_PER_EXTEND) {
+        instructions.push(PlannedInstruction {
+            program_id,
+            accounts: accounts(&EXTEND_LOOKUP_TABLE_ACCOUNTS),
+            data: InstructionData::Extend {
+                addresses: chunk.to_vec(),
+            }
+            .serialize(),
+        });
+    }
+
+    Ok(TablePlan {
+        table_address,
+        transactions_needed: instructions.len().saturating_sub(1).max(1),
+        instructions,
+        estimated_rent_lamports: rent
+            .minimum_balance(LOOKUP_TABLE_META_SIZE + addresses.len() * PUBKEY_BYTES),
+    })
+}
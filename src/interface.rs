@@ -0,0 +1,217 @@
+//! The program's wire format as plain data: instruction discriminators, the
+//! table account layout, error codes and the address seeds. Nothing here
+//! depends on more than `core`, so the processor, the client helpers and the
+//! `parse-only` build all read these values from the same place instead of
+//! repeating the literals.
+
+pub const PUBKEY_BYTES: usize = 32;
+
+// Instruction discriminators, the little endian `u32` every instruction's
+// data starts with.
+
+pub const CREATE_LOOKUP_TABLE: u32 = 0;
+pub const FREEZE_LOOKUP_TABLE: u32 = 1;
+pub const EXTEND_LOOKUP_TABLE: u32 = 2;
+pub const DEACTIVATE_LOOKUP_TABLE: u32 = 3;
+pub const CLOSE_LOOKUP_TABLE: u32 = 4;
+pub const COLLECT_ADDRESSES: u32 = 5;
+pub const CREATE_LOOKUP_TABLE_FROM_CLOCK: u32 = 6;
+pub const EXTEND_LOOKUP_TABLE_CHECKED: u32 = 7;
+pub const TRANSFER_AUTHORITY: u32 = 8;
+pub const TOP_UP_RENT: u32 = 9;
+pub const ADDRESSES_READY: u32 = 10;
+pub const REACTIVATE_LOOKUP_TABLE: u32 = 11;
+pub const EXTEND_LOOKUP_TABLE_WITH_ALLOWLIST: u32 = 12;
+pub const RENT_STATUS: u32 = 13;
+pub const GET_AGE: u32 = 14;
+pub const GET_VERSION: u32 = 15;
+pub const EXTEND_ALLOWLIST: u32 = 16;
+pub const CLEAR_ALLOWLIST: u32 = 17;
+
+/// Length of the `RentStatus` return data: the current and the required
+/// lamports as little endian `u64`s, then a byte that is `1` when the
+/// account is rent exempt.
+pub const RENT_STATUS_LEN: usize = 8 + 8 + 1;
+
+// `PROGRAM_VERSION_MAJOR`, `PROGRAM_VERSION_MINOR` and
+// `PROGRAM_VERSION_PATCH`, the crate version split by build.rs.
+include!(concat!(env!("OUT_DIR"), "/version.rs"));
+
+// Capabilities reported by `GetVersion`, so tooling can tell builds apart
+// without knowing their version history.
+
+/// Accepts instructions encoded by the native program's builders.
+pub const FEATURE_NATIVE_INSTRUCTIONS: u32 = 1 << 0;
+/// `ExtendLookupTable` only needs a payer when the table lacks rent.
+pub const FEATURE_PAYER_OPTIONAL_EXTEND: u32 = 1 << 1;
+/// `TransferAuthority` is supported.
+pub const FEATURE_TRANSFER_AUTHORITY: u32 = 1 << 2;
+/// `CloseLookupTable` takes a flags byte.
+pub const FEATURE_CLOSE_FLAGS: u32 = 1 << 3;
+/// `ReactivateLookupTable` is supported.
+pub const FEATURE_REACTIVATE: u32 = 1 << 4;
+/// `ExtendLookupTableWithAllowlist`, `ExtendAllowlist` and `ClearAllowlist`
+/// are supported.
+pub const FEATURE_EXTEND_WITH_ALLOWLIST: u32 = 1 << 5;
+/// `CloseLookupTable` understands [`CLOSE_BURN_LAMPORTS`].
+pub const FEATURE_CLOSE_BURN: u32 = 1 << 6;
+/// Everything this build supports.
+pub const PROGRAM_FEATURES: u32 = FEATURE_NATIVE_INSTRUCTIONS
+    | FEATURE_PAYER_OPTIONAL_EXTEND
+    | FEATURE_TRANSFER_AUTHORITY
+    | FEATURE_CLOSE_FLAGS
+    | FEATURE_REACTIVATE
+    | FEATURE_EXTEND_WITH_ALLOWLIST
+    | FEATURE_CLOSE_BURN;
+
+/// Length of the `GetVersion` return data: the major and minor version
+/// bytes, the little endian `u16` patch version and the `u32` feature mask.
+pub const GET_VERSION_LEN: usize = 1 + 1 + 2 + 4;
+
+/// The version and features a deployed build reports through `GetVersion`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgramVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u16,
+    pub features: u32,
+}
+
+impl ProgramVersion {
+    /// The version of this build.
+    pub const CURRENT: Self = Self {
+        major: PROGRAM_VERSION_MAJOR,
+        minor: PROGRAM_VERSION_MINOR,
+        patch: PROGRAM_VERSION_PATCH,
+        features: PROGRAM_FEATURES,
+    };
+
+    pub const fn to_bytes(&self) -> [u8; GET_VERSION_LEN] {
+        let patch = self.patch.to_le_bytes();
+        let features = self.features.to_le_bytes();
+        [
+            self.major,
+            self.minor,
+            patch[0],
+            patch[1],
+            features[0],
+            features[1],
+            features[2],
+            features[3],
+        ]
+    }
+
+    /// Decodes `GetVersion` return data, `None` unless it is exactly
+    /// [`GET_VERSION_LEN`] bytes.
+    pub fn from_return_data(data: &[u8]) -> Option<Self> {
+        let data: &[u8; GET_VERSION_LEN] = data.try_into().ok()?;
+        Some(Self {
+            major: data[0],
+            minor: data[1],
+            patch: u16::from_le_bytes([data[2], data[3]]),
+            features: u32::from_le_bytes([data[4], data[5], data[6], data[7]]),
+        })
+    }
+
+    /// Whether every capability in `features` is reported.
+    pub const fn supports(&self, features: u32) -> bool {
+        self.features & features == features
+    }
+}
+
+/// Optional `CloseLookupTable` flag, passed as a byte after the
+/// discriminator: reject recipients owned by this program, so closing can't
+/// accidentally fund another lookup table.
+pub const CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT: u8 = 1 << 0;
+/// Optional `CloseLookupTable` flag: burn the reclaimed lamports. The
+/// runtime doesn't let an instruction destroy lamports, so the recipient
+/// must be [`INCINERATOR_ID`], whose balance is burned at the end of the
+/// block.
+pub const CLOSE_BURN_LAMPORTS: u8 = 1 << 1;
+/// All flags `CloseLookupTable` understands.
+pub const CLOSE_FLAGS: u8 = CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT | CLOSE_BURN_LAMPORTS;
+
+/// `1nc1nerator11111111111111111111111111111111`, the account whose
+/// lamports the runtime burns.
+pub const INCINERATOR_ID: [u8; PUBKEY_BYTES] = [
+    0, 51, 144, 114, 141, 52, 17, 96, 121, 189, 201, 17, 191, 255, 0, 219, 212, 77, 46, 205, 204,
+    247, 156, 166, 225, 0, 56, 225, 0, 0, 0, 0,
+];
+
+// Table account layout: a `u32` discriminator, the meta fields, then the
+// addresses packed back to back.
+
+/// Account discriminator of an initialized lookup table.
+pub const LOOKUP_TABLE_DISCRIMINATOR: u32 = 1;
+pub const DEACTIVATION_SLOT_OFFSET: usize = 4;
+pub const LAST_EXTENDED_SLOT_OFFSET: usize = 12;
+pub const LAST_EXTENDED_SLOT_START_INDEX_OFFSET: usize = 20;
+/// Non-zero while the table has an authority, zero once frozen.
+pub const AUTHORITY_TAG_OFFSET: usize = 21;
+pub const AUTHORITY_OFFSET: usize = 22;
+pub const VERSION_OFFSET: usize = 54;
+pub const DEACTIVATION_REASON_OFFSET: usize = 55;
+/// Offset of the first address.
+pub const LOOKUP_TABLE_META_SIZE: usize = 56;
+pub const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
+
+/// Byte offset of the address at `index` in a table's account data, for
+/// reading or writing a single address without decoding the table. `None`
+/// for an index no table can hold.
+pub const fn address_offset(index: usize) -> Option<usize> {
+    if index < LOOKUP_TABLE_MAX_ADDRESSES {
+        Some(LOOKUP_TABLE_META_SIZE + index * PUBKEY_BYTES)
+    } else {
+        None
+    }
+}
+
+/// Errors specific to this program, returned as `ProgramError::Custom` so
+/// clients can tell them apart from the runtime's generic errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum LookupTableError {
+    /// The instruction discriminator is not one this version of the program
+    /// knows, as opposed to a known instruction with malformed data.
+    UnknownInstruction = 0,
+    /// An address passed to `ExtendLookupTableWithAllowlist` is not in the
+    /// allowlist.
+    AddressNotAllowed = 1,
+    /// `ExtendLookupTableChecked` was used on a table whose `version`
+    /// reached `u8::MAX`. The counter stays there rather than wrap, so a
+    /// matching version no longer proves the table is unchanged.
+    VersionExhausted = 2,
+}
+
+/// Seeds of a lookup table address, in derivation order: the authority, the
+/// little endian recent slot and the bump.
+#[inline(always)]
+pub fn lookup_table_seeds<'a>(
+    authority: &'a [u8; PUBKEY_BYTES],
+    recent_slot: &'a [u8; 8],
+    bump_seed: &'a [u8; 1],
+) -> [&'a [u8]; 3] {
+    [authority, recent_slot, bump_seed]
+}
+
+/// First seed of a table's allowlist address.
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+
+// Allowlist account layout: the key that controls the table while the
+// allowlist is bound, the bump of the allowlist address, then the allowed
+// addresses packed back to back in ascending byte order.
+
+pub const ALLOWLIST_CONTROLLER_OFFSET: usize = 0;
+pub const ALLOWLIST_BUMP_OFFSET: usize = 32;
+/// Offset of the first allowed address.
+pub const ALLOWLIST_HEADER_SIZE: usize = 33;
+
+/// Seeds of a table's allowlist address, in derivation order: the allowlist
+/// seed, the table and the bump.
+#[inline(always)]
+pub fn allowlist_seeds<'a>(
+    lookup_table: &'a [u8; PUBKEY_BYTES],
+    bump_seed: &'a [u8; 1],
+) -> [&'a [u8]; 3] {
+    [ALLOWLIST_SEED, lookup_table, bump_seed]
+}
@@ -0,0 +1,143 @@
+//! Finding this program's instructions in the instructions sysvar, for
+//! programs that require a table change in the same transaction as their
+//! own instruction, e.g. to keep a registry and a table in sync.
+
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::instruction::ProgramInstruction;
+use crate::interface::PUBKEY_BYTES;
+
+/// Each account of a serialized instruction is a flags byte and the key.
+const ACCOUNT_META_SIZE: usize = 1 + PUBKEY_BYTES;
+const IS_SIGNER: u8 = 1 << 0;
+const IS_WRITABLE: u8 = 1 << 1;
+
+/// The accounts of an instruction, read in place from the sysvar data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstructionAccounts<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> InstructionAccounts<'a> {
+    pub fn len(&self) -> usize {
+        self.data.len() / ACCOUNT_META_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn key(&self, index: usize) -> Option<&'a Pubkey> {
+        self.meta(index).and_then(|meta| meta[1..].try_into().ok())
+    }
+
+    pub fn is_signer(&self, index: usize) -> Option<bool> {
+        self.meta(index).map(|meta| meta[0] & IS_SIGNER != 0)
+    }
+
+    pub fn is_writable(&self, index: usize) -> Option<bool> {
+        self.meta(index).map(|meta| meta[0] & IS_WRITABLE != 0)
+    }
+
+    /// The account keys in instruction order.
+    pub fn keys(&self) -> impl Iterator<Item = &'a Pubkey> {
+        self.data
+            .chunks_exact(ACCOUNT_META_SIZE)
+            .filter_map(|meta| meta[1..].try_into().ok())
+    }
+
+    fn meta(&self, index: usize) -> Option<&'a [u8]> {
+        let start = index.checked_mul(ACCOUNT_META_SIZE)?;
+        self.data.get(start..start.checked_add(ACCOUNT_META_SIZE)?)
+    }
+}
+
+/// Instructions of a transaction that target `program_id`, decoded with
+/// [`ProgramInstruction::unpack`], with their index in the transaction.
+pub struct LookupTableInstructions<'a> {
+    data: &'a [u8],
+    program_id: &'a Pubkey,
+    index: usize,
+    len: usize,
+}
+
+impl<'a> Iterator for LookupTableInstructions<'a> {
+    /// A malformed sysvar entry, or instruction data for `program_id` that
+    /// doesn't decode, is returned as an error in its place.
+    type Item = Result<(usize, ProgramInstruction<'a>, InstructionAccounts<'a>), ProgramError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.len {
+            let index = self.index;
+            self.index += 1;
+
+            match instruction_at(self.data, index) {
+                Ok((program_id, _, _)) if program_id != self.program_id => continue,
+                Ok((_, accounts, data)) => {
+                    return Some(
+                        ProgramInstruction::unpack(data)
+                            .map(|instruction| (index, instruction, accounts)),
+                    )
+                }
+                Err(error) => return Some(Err(error)),
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterates over the instructions for `program_id` in `instructions_sysvar`,
+/// the data of the instructions sysvar account. Checking that the data comes
+/// from that account is left to the caller.
+pub fn lookup_table_instructions<'a>(
+    instructions_sysvar: &'a [u8],
+    program_id: &'a Pubkey,
+) -> Result<LookupTableInstructions<'a>, ProgramError> {
+    Ok(LookupTableInstructions {
+        data: instructions_sysvar,
+        program_id,
+        index: 0,
+        len: read_u16(instructions_sysvar, 0)? as usize,
+    })
+}
+
+/// Reads the instruction at `index`: a `u16` offset table after the
+/// instruction count points at each instruction, serialized as its account
+/// count, the accounts, the program id, the data length and the data.
+fn instruction_at(
+    data: &[u8],
+    index: usize,
+) -> Result<(&Pubkey, InstructionAccounts<'_>, &[u8]), ProgramError> {
+    let offset = read_u16(data, 2 + index * 2)? as usize;
+
+    let accounts_len = read_u16(data, offset)? as usize;
+    let accounts_start = offset + 2;
+    let program_id_start = accounts_start + accounts_len * ACCOUNT_META_SIZE;
+    let data_len_start = program_id_start + PUBKEY_BYTES;
+    let data_start = data_len_start + 2;
+    let data_end = data_start + read_u16(data, data_len_start)? as usize;
+
+    let program_id = data
+        .get(program_id_start..data_len_start)
+        .and_then(|key| key.try_into().ok())
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let instruction_data = data
+        .get(data_start..data_end)
+        .ok_or(ProgramError::InvalidAccountData)?;
+
+    Ok((
+        program_id,
+        InstructionAccounts {
+            data: &data[accounts_start..program_id_start],
+        },
+        instruction_data,
+    ))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, ProgramError> {
+    data.get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+        .ok_or(ProgramError::InvalidAccountData)
+}
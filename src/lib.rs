@@ -1,5 +1,63 @@
 #![no_std]
 
+#[cfg(feature = "client")]
+extern crate alloc;
+// Off-chain, the `cdylib` needs a panic handler and, with `client`, a global
+// allocator. The program entrypoint only sets these up on-chain, and builds
+// without it (`parse-only`, `no-entrypoint`) have none, so link `std` for
+// them the way pinocchio does outside the `solana` target.
+#[cfg(any(feature = "std", not(target_os = "solana")))]
+extern crate std;
+
+#[cfg(feature = "anchor")]
+pub mod anchor;
+#[cfg(feature = "client")]
+mod base58;
+#[cfg(all(feature = "program", not(feature = "no-entrypoint")))]
 mod entrypoint;
-mod processor;
-mod state;
\ No newline at end of file
+#[cfg(feature = "program")]
+pub mod error;
+#[cfg(feature = "program")]
+pub mod instruction;
+pub mod interface;
+#[cfg(feature = "program")]
+pub mod introspection;
+#[cfg(feature = "program")]
+pub mod log;
+#[cfg(feature = "client")]
+pub mod message;
+pub mod prelude;
+#[cfg(feature = "program")]
+pub mod processor;
+#[cfg(feature = "std")]
+pub mod rpc;
+pub mod state;
+#[cfg(feature = "elf")]
+pub mod testing;
+
+use state::Pubkey;
+
+/// Read-only view of a lookup table account. It needs neither `std` nor an
+/// allocator, so on-chain programs can use it to read tables passed to them.
+pub use state::LookupTable;
+
+// `LOOKUP_TABLE_PROGRAM_ID`, the canonical address unless overridden through
+// the `custom-program-id` feature (see build.rs), and
+// `CANONICAL_LOOKUP_TABLE_PROGRAM_ID`.
+include!(concat!(env!("OUT_DIR"), "/program_id.rs"));
+
+/// `LOOKUP_TABLE_PROGRAM_ID` under the name `solana-address-lookup-table-interface`
+/// uses, so code written against it ports unchanged.
+pub const ID: Pubkey = LOOKUP_TABLE_PROGRAM_ID;
+
+/// Returns the program id, like the interface crate's `program::id()`.
+#[inline(always)]
+pub const fn id() -> Pubkey {
+    ID
+}
+
+/// Whether `id` is the program id this crate is built for.
+#[inline(always)]
+pub fn check_id(id: &Pubkey) -> bool {
+    id == &LOOKUP_TABLE_PROGRAM_ID
+}
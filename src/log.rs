@@ -0,0 +1,93 @@
+use pinocchio::pubkey::{self, Pubkey};
+use pinocchio_log::{log, logger::Logger};
+
+/// Events logged on the success path of the processor. Keeping them in one
+/// place keeps the wording consistent for indexers parsing the logs.
+pub enum ProgramLog {
+    Create {
+        authority: Pubkey,
+        slot: u64,
+    },
+    Freeze {
+        table: Pubkey,
+    },
+    /// `payer` is set when the extension needed a rent top-up. The meta has
+    /// no room for the key without breaking the native layout, so the logs
+    /// are where the funding payer is recorded.
+    Extend {
+        table: Pubkey,
+        new_count: usize,
+        payer: Option<Pubkey>,
+    },
+    Deactivate {
+        table: Pubkey,
+        slot: u64,
+    },
+    Close {
+        table: Pubkey,
+        recipient: Pubkey,
+    },
+}
+
+/// Room for the longest message line, "Created lookup table for slot {}
+/// with authority" with a 20-digit slot.
+const MESSAGE_LEN: usize = 80;
+
+/// Formats the message line of `event`, without the addresses [`emit`] logs
+/// after it. Separate from `emit` so the wording can be checked without
+/// running the program.
+pub fn format_log(event: &ProgramLog) -> Logger<MESSAGE_LEN> {
+    let mut logger = Logger::default();
+    match event {
+        ProgramLog::Create { slot, .. } => {
+            logger
+                .append("Created lookup table for slot ")
+                .append(*slot)
+                .append(" with authority");
+        }
+        ProgramLog::Freeze { .. } => {
+            logger.append("Froze lookup table");
+        }
+        ProgramLog::Extend {
+            new_count, payer, ..
+        } => {
+            logger
+                .append("Extended lookup table to ")
+                .append(*new_count)
+                .append(" addresses");
+            if payer.is_some() {
+                logger.append(", funded by payer");
+            }
+        }
+        ProgramLog::Deactivate { slot, .. } => {
+            logger
+                .append("Deactivated lookup table at slot ")
+                .append(*slot);
+        }
+        ProgramLog::Close { .. } => {
+            logger.append("Closed lookup table");
+        }
+    }
+    logger
+}
+
+/// Logs `event`: the line from [`format_log`], then the addresses with
+/// `sol_log_pubkey`, which prints them in base58, each on its own line.
+pub fn emit(event: &ProgramLog) {
+    format_log(event).log();
+    match event {
+        ProgramLog::Create { authority, .. } => pubkey::log(authority),
+        ProgramLog::Freeze { table } | ProgramLog::Deactivate { table, .. } => pubkey::log(table),
+        ProgramLog::Extend { table, payer, .. } => {
+            pubkey::log(table);
+            if let Some(payer) = payer {
+                pubkey::log(payer);
+            }
+        }
+        ProgramLog::Close { table, recipient } => {
+            pubkey::log(table);
+            log!("Reclaimed lamports sent to");
+            pubkey::log(recipient);
+        }
+    }
+}
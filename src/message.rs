@@ -0,0 +1,93 @@
+//! Resolution of v0 message address table lookups against raw table data,
+//! following the runtime's rules, for offline signing and simulation.
+
+use alloc::vec::Vec;
+
+use crate::state::{LookupTable, LookupTableStatus, Pubkey, Slot};
+
+/// A v0 message's reference to a lookup table, as in `solana-message`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MessageAddressTableLookup {
+    pub account_key: Pubkey,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
+
+/// Addresses loaded through a lookup, split by access like the runtime's
+/// `LoadedAddresses`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadedAddresses {
+    pub writable: Vec<Pubkey>,
+    pub readonly: Vec<Pubkey>,
+}
+
+/// Mirrors the runtime's `AddressLookupError` for the cases that depend on
+/// the table data alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LookupError {
+    /// The table is fully deactivated, which the runtime reports as a
+    /// missing account.
+    LookupTableAccountNotFound,
+    /// The data is not an initialized lookup table.
+    InvalidAccountData,
+    /// An index is past the addresses active at the current slot.
+    InvalidLookupIndex,
+}
+
+/// Resolves `lookup` against `table_data`, the data of the account at
+/// `lookup.account_key`, at `current_slot`. `slot_hashes` is the raw slot
+/// hashes sysvar data, which decides whether a deactivated table is still
+/// usable. Checking the account owner is left to the caller.
+pub fn resolve_lookup(
+    table_data: &[u8],
+    lookup: &MessageAddressTableLookup,
+    current_slot: Slot,
+    slot_hashes: &[u8],
+) -> Result<LoadedAddresses, LookupError> {
+    let table = LookupTable::from_bytes(table_data).map_err(|_| LookupError::InvalidAccountData)?;
+    let meta = table.meta();
+
+    if meta.status(current_slot, slot_hashes) == LookupTableStatus::Deactivated {
+        return Err(LookupError::LookupTableAccountNotFound);
+    }
+
+    let active_addresses = table.active_addresses(current_slot);
+    let resolve = |indexes: &[u8]| {
+        indexes
+            .iter()
+            .map(|index| {
+                active_addresses
+                    .get(*index as usize)
+                    .copied()
+                    .ok_or(LookupError::InvalidLookupIndex)
+            })
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    Ok(LoadedAddresses {
+        writable: resolve(&lookup.writable_indexes)?,
+        readonly: resolve(&lookup.readonly_indexes)?,
+    })
+}
+
+/// Splits the addresses of `table_data` into those a message compiled for
+/// `current_slot` can reference and those appended during that slot, which
+/// only become usable in the next one. Deactivation is not considered here:
+/// [`resolve_lookup`] checks it against the slot hashes.
+pub fn usable_addresses(
+    table_data: &[u8],
+    current_slot: Slot,
+) -> Result<(Vec<Pubkey>, Vec<Pubkey>), LookupError> {
+    let table = LookupTable::from_bytes(table_data).map_err(|_| LookupError::InvalidAccountData)?;
+    let active = table.active_addresses(current_slot);
+    let pending = &table.addresses()[active.len()..];
+    Ok((active.to_vec(), pending.to_vec()))
+}
+
+/// Whether a message compiled for `current_slot` can reference `key` through
+/// the table in `table_data`. Data that is not a lookup table holds nothing
+/// to reference.
+pub fn can_reference(table_data: &[u8], key: &Pubkey, current_slot: Slot) -> bool {
+    LookupTable::from_bytes(table_data)
+        .is_ok_and(|table| table.active_addresses(current_slot).contains(key))
+}
@@ -0,0 +1,10 @@
+//! The types this crate's public API is written in, re-exported so that
+//! integrators can name them without depending on pinocchio themselves and
+//! risking a version mismatch.
+
+pub use crate::interface::LookupTableError;
+pub use crate::state::{LookupTable, LookupTableMeta, LookupTableStatus, Pubkey, Slot, StateError};
+pub use crate::{check_id, LOOKUP_TABLE_PROGRAM_ID};
+
+#[cfg(feature = "program")]
+pub use pinocchio::{program_error::ProgramError, sysvars::rent::Rent};
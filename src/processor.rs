@@ -1,12 +1,13 @@
 use pinocchio::{
     account_info::AccountInfo,
+    cpi::set_return_data,
     instruction::{Seed, Signer},
     program_error::ProgramError,
-    pubkey::{create_program_address, Pubkey, PUBKEY_BYTES},
+    pubkey::{create_program_address, find_program_address, Pubkey, PUBKEY_BYTES},
     sysvars::{
         clock::{Clock, Slot},
         rent::Rent,
-        slot_hashes::{SlotHashes, MAX_ENTRIES, SLOTHASHES_ID},
+        slot_hashes::{SlotHashes, SLOTHASHES_ID},
         Sysvar,
     },
     ProgramResult,
@@ -14,93 +15,352 @@ use pinocchio::{
 use pinocchio_log::log;
 use pinocchio_system::instructions;
 
-use crate::state::{
-    serialize_new_lookup_table, LookupTableMeta, LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE,
+use crate::interface::{
+    address_offset, allowlist_seeds, lookup_table_seeds, LookupTableError, ProgramVersion,
+    ALLOWLIST_BUMP_OFFSET, ALLOWLIST_CONTROLLER_OFFSET, ALLOWLIST_HEADER_SIZE, ALLOWLIST_SEED,
+    CLOSE_BURN_LAMPORTS, CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT, INCINERATOR_ID,
+    LOOKUP_TABLE_DISCRIMINATOR, LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE,
+    RENT_STATUS_LEN,
 };
+use crate::log::{emit, ProgramLog};
+use crate::state::{serialize_new_lookup_table, LookupTableMeta, MAX_COLLECTED_ADDRESSES};
 
+/// Creates a lookup table for `authority`, funded by `payer`. The two may be
+/// the same account, in which case it pays for the table it controls.
 pub fn process_create_lookup_table(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     untrusted_recent_slot: Slot,
     bump_seed: u8,
 ) -> ProgramResult {
-    let [lookup_table_info, authority_info, payer_info, slot_hashes_info, _system_program] =
-        accounts
-    else {
+    // The native program takes `[table, authority, payer, system_program]`
+    // and reads the slot hashes through a syscall. The sysvar account may be
+    // passed as well, which is cheaper than the syscall lookups.
+    let [lookup_table_info, authority_info, payer_info, remaining @ ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
+    let slot_hashes_info = remaining.iter().find(|info| info.key() == &SLOTHASHES_ID);
 
     if !payer_info.is_signer() {
         return Err(ProgramError::MissingRequiredSignature);
     }
-    if slot_hashes_info.key() != &SLOTHASHES_ID {
+
+    check_recent_slot(slot_hashes_info, untrusted_recent_slot)?;
+    let derivation_slot = untrusted_recent_slot;
+
+    let slot_bytes = derivation_slot.to_le_bytes();
+    let bump_ref = [bump_seed];
+    let derived_table_seeds = lookup_table_seeds(authority_info.key(), &slot_bytes, &bump_ref);
+
+    let derived_table_key =
+        create_program_address(&derived_table_seeds, program_id).map_err(|_| {
+            log!(
+                "Bump seed {} does not derive a valid table address",
+                bump_seed
+            );
+            ProgramError::InvalidSeeds
+        })?;
+
+    if lookup_table_info.key() != &derived_table_key {
+        log!("Table address must match derived address");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    create_lookup_table_account(
+        program_id,
+        lookup_table_info,
+        authority_info,
+        payer_info,
+        derivation_slot,
+        bump_seed,
+    )?;
+
+    Ok(())
+}
+
+/// Creates a lookup table derived from the most recent slot in the slot
+/// hashes. That is the last slot that produced a block, not the one before
+/// the current slot, which may have been skipped and would then fail the
+/// recent slot check. Since the client does not pick the slot, the table
+/// address, slot and bump are returned as return data.
+///
+/// The address changes with every new block, so a client learns it by
+/// simulating the transaction and must resend if the transaction lands after
+/// another block. A mismatch logs the address expected at execution.
+pub fn process_create_lookup_table_from_clock(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    // The native program takes `[table, authority, payer, system_program]`
+    // and reads the slot hashes through a syscall. The sysvar account may be
+    // passed as well, which is cheaper than the syscall lookups.
+    let [lookup_table_info, authority_info, payer_info, remaining @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let slot_hashes_info = remaining.iter().find(|info| info.key() == &SLOTHASHES_ID);
+
+    if !payer_info.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let derivation_slot = most_recent_slot(slot_hashes_info)?;
+
+    let (derived_table_key, bump_seed) = find_program_address(
+        &[
+            authority_info.key().as_ref(),
+            &derivation_slot.to_le_bytes(),
+        ],
+        program_id,
+    );
+
+    if lookup_table_info.key() != &derived_table_key {
+        log!(
+            "Table address must match the address derived from slot {}",
+            derivation_slot
+        );
+        pinocchio::pubkey::log(&derived_table_key);
         return Err(ProgramError::InvalidArgument);
     }
 
-    let derivation_slot = {
-        let slot_hashes = SlotHashes::from_account_info(slot_hashes_info)?;
-        if slot_hashes
+    create_lookup_table_account(
+        program_id,
+        lookup_table_info,
+        authority_info,
+        payer_info,
+        derivation_slot,
+        bump_seed,
+    )?;
+
+    let mut return_data = [0u8; PUBKEY_BYTES + 8 + 1];
+    return_data[..PUBKEY_BYTES].copy_from_slice(&derived_table_key);
+    return_data[PUBKEY_BYTES..PUBKEY_BYTES + 8].copy_from_slice(&derivation_slot.to_le_bytes());
+    return_data[PUBKEY_BYTES + 8] = bump_seed;
+    set_return_data(&return_data);
+
+    Ok(())
+}
+
+fn check_recent_slot(slot_hashes_info: Option<&AccountInfo>, slot: Slot) -> ProgramResult {
+    let is_recent = match slot_hashes_info {
+        Some(slot_hashes_info) => SlotHashes::from_account_info(slot_hashes_info)?
             .entries()
             .iter()
-            .any(|e| e.slot() == untrusted_recent_slot)
-        {
-            untrusted_recent_slot
-        } else {
-            log!("{} is not a recent slot", untrusted_recent_slot);
-            return Err(ProgramError::InvalidInstructionData);
+            .any(|e| e.slot() == slot),
+        None => slot_hashes_position(slot)?.is_some(),
+    };
+
+    if !is_recent {
+        log!("{} is not a recent slot", slot);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(())
+}
+
+/// The most recent slot in the slot hashes sysvar, read from the account when
+/// it is passed and through `sol_get_sysvar` otherwise.
+fn most_recent_slot(slot_hashes_info: Option<&AccountInfo>) -> Result<Slot, ProgramError> {
+    let slot = match slot_hashes_info {
+        Some(slot_hashes_info) => SlotHashes::from_account_info(slot_hashes_info)?
+            .entries()
+            .first()
+            .map(|entry| entry.slot()),
+        None => match slot_hashes_len()? {
+            0 => None,
+            _ => Some(read_slot_hashes_u64(8)?),
+        },
+    };
+
+    slot.ok_or_else(|| {
+        log!("Slot hashes are empty");
+        ProgramError::InvalidAccountData
+    })
+}
+
+/// Number of entries in the slot hashes sysvar, read through `sol_get_sysvar`.
+/// This is the cluster's actual capacity once it has produced enough blocks,
+/// which need not be the compiled-in `MAX_ENTRIES`.
+fn slot_hashes_len() -> Result<usize, ProgramError> {
+    read_slot_hashes_u64(0).map(|len| len as usize)
+}
+
+/// Position of `slot` in the slot hashes sysvar, read through `sol_get_sysvar`
+/// for callers that don't pass the sysvar account. Entries are sorted by
+/// descending slot, so a binary search needs only a handful of 8-byte reads
+/// instead of copying the whole sysvar.
+fn slot_hashes_position(slot: Slot) -> Result<Option<usize>, ProgramError> {
+    const ENTRY_SIZE: usize = 8 + 32;
+
+    let (mut low, mut high) = (0, slot_hashes_len()?);
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let mid_slot = read_slot_hashes_u64(8 + mid * ENTRY_SIZE)?;
+
+        match mid_slot.cmp(&slot) {
+            core::cmp::Ordering::Equal => return Ok(Some(mid)),
+            core::cmp::Ordering::Greater => low = mid + 1,
+            core::cmp::Ordering::Less => high = mid,
         }
+    }
+
+    Ok(None)
+}
+
+#[cfg(target_os = "solana")]
+fn read_slot_hashes_u64(offset: usize) -> Result<u64, ProgramError> {
+    let mut bytes = [0u8; 8];
+    let result = unsafe {
+        pinocchio::syscalls::sol_get_sysvar(
+            SLOTHASHES_ID.as_ptr(),
+            bytes.as_mut_ptr(),
+            offset as u64,
+            bytes.len() as u64,
+        )
     };
 
-    let derived_table_seeds = &[
-        authority_info.key().as_ref(),
-        &derivation_slot.to_le_bytes(),
-        &[bump_seed],
-    ];
+    if result != pinocchio::SUCCESS {
+        return Err(result.into());
+    }
 
-    let derived_table_key = create_program_address(derived_table_seeds, program_id)?;
+    Ok(u64::from_le_bytes(bytes))
+}
 
-    if lookup_table_info.key() != &derived_table_key {
-        log!("Table address must match derived address");
+#[cfg(not(target_os = "solana"))]
+fn read_slot_hashes_u64(_offset: usize) -> Result<u64, ProgramError> {
+    Err(ProgramError::UnsupportedSysvar)
+}
+
+fn create_lookup_table_account(
+    program_id: &Pubkey,
+    lookup_table_info: &AccountInfo,
+    authority_info: &AccountInfo,
+    payer_info: &AccountInfo,
+    derivation_slot: Slot,
+    bump_seed: u8,
+) -> ProgramResult {
+    if lookup_table_info.key() == payer_info.key() {
+        log!("Lookup table cannot pay for its own creation");
         return Err(ProgramError::InvalidArgument);
     }
 
-    if lookup_table_info.owner() == program_id {
+    // Creating an existing table is a no-op, so retries are harmless. It
+    // logs nothing, since no table was created.
+    if lookup_table_info.owner() == program_id && !lookup_table_info.data_is_empty() {
         return Ok(());
     }
 
-    let rent = <Rent as Sysvar>::get()?;
-    let required_lamports = rent
-        .minimum_balance(LOOKUP_TABLE_META_SIZE as usize)
-        .max(1)
-        .saturating_sub(lookup_table_info.lamports());
-
     let slot_bytes = derivation_slot.to_le_bytes();
     let bump_ref = [bump_seed];
-
     let seeds = [
         Seed::from(authority_info.key().as_ref()),
         Seed::from(&slot_bytes),
         Seed::from(&bump_ref),
     ];
-    // Combined into one CPI, rather than the three CPI, will save cu
-    instructions::CreateAccount {
-        from: payer_info,
-        to: lookup_table_info,
-        lamports: required_lamports,
-        space: LOOKUP_TABLE_META_SIZE as u64,
-        owner: program_id,
-    }
-    .invoke_signed(&[Signer::from(&seeds)])?;
 
-    let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
+    create_program_account(
+        program_id,
+        lookup_table_info,
+        payer_info,
+        LOOKUP_TABLE_META_SIZE,
+        &[Signer::from(&seeds)],
+    )?;
 
+    let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
     serialize_new_lookup_table(data, authority_info.key())?;
 
+    emit(&ProgramLog::Create {
+        authority: *authority_info.key(),
+        slot: derivation_slot,
+    });
+
+    Ok(())
+}
+
+/// Creates a rent-exempt account of `space` bytes owned by this program, at
+/// an address `signers` sign for, funded by `payer`.
+fn create_program_account(
+    program_id: &Pubkey,
+    account_info: &AccountInfo,
+    payer_info: &AccountInfo,
+    space: usize,
+    signers: &[Signer],
+) -> ProgramResult {
+    let rent = <Rent as Sysvar>::get()?;
+    let required_lamports = rent
+        .minimum_balance(space)
+        .max(1)
+        .saturating_sub(account_info.lamports());
+
+    if account_info.owner() == program_id {
+        // Still owned by this program but without data, e.g. closed earlier
+        // in the same transaction: initialize it in place.
+        account_info.resize(space)?;
+
+        if required_lamports > 0 {
+            instructions::Transfer {
+                from: payer_info,
+                to: account_info,
+                lamports: required_lamports,
+            }
+            .invoke()?;
+        }
+    } else if account_info.lamports() == 0 {
+        // Combined into one CPI, rather than the three CPI, will save cu
+        instructions::CreateAccount {
+            from: payer_info,
+            to: account_info,
+            lamports: required_lamports,
+            space: space as u64,
+            owner: program_id,
+        }
+        .invoke_signed(signers)?;
+    } else {
+        // The system program refuses to create an account that already
+        // holds lamports, so a pre-funded account is topped up, allocated
+        // and assigned separately like the native program does.
+        if required_lamports > 0 {
+            instructions::Transfer {
+                from: payer_info,
+                to: account_info,
+                lamports: required_lamports,
+            }
+            .invoke()?;
+        }
+
+        instructions::Allocate {
+            account: account_info,
+            space: space as u64,
+        }
+        .invoke_signed(signers)?;
+
+        instructions::Assign {
+            account: account_info,
+            owner: program_id,
+        }
+        .invoke_signed(signers)?;
+    }
+
     Ok(())
 }
 
+/// Decodes the meta of a lookup table account. Owner checks alone can't
+/// tell a table from the program's other accounts, such as allowlists, so
+/// the data must also start with the lookup table discriminator.
+fn read_lookup_table_meta(data: &[u8]) -> Result<LookupTableMeta, ProgramError> {
+    if data.len() < LOOKUP_TABLE_META_SIZE {
+        log!("Lookup table account data is too small");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if data[0..4] != LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes() {
+        log!("Account is not a lookup table");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(LookupTableMeta::read(data))
+}
+
 pub fn process_freeze_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    let [lookup_table_info, authority_info] = accounts else {
+    let [lookup_table_info, authority_info, ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
@@ -114,11 +374,11 @@ pub fn process_freeze_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo]
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let lookup_table_meta = {
+    let mut lookup_table_meta = {
         let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
-        let meta = unsafe { &mut *(data.as_mut_ptr().add(4) as *mut LookupTableMeta) };
+        let meta = read_lookup_table_meta(data)?;
 
-        if meta.authority_tag == 0 {
+        if meta.is_frozen() {
             log!("Lookup table is already frozen");
             return Err(ProgramError::Immutable);
         }
@@ -126,8 +386,19 @@ pub fn process_freeze_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo]
             log!("Incorrect lookup table authority");
             return Err(ProgramError::IncorrectAuthority);
         }
-        if meta.deactivation_slot != Slot::MAX {
-            log!("Deactivated tables cannot be frozen");
+        if meta.is_deactivated() {
+            // Only the error message depends on the cooldown, so the sysvars
+            // are read on this path alone. The table is fully deactivated by
+            // the rule close applies: its deactivation slot is no longer in
+            // the slot hashes.
+            let clock = <Clock as Sysvar>::get()?;
+            if meta.deactivation_slot != clock.slot
+                && slot_hashes_position(meta.deactivation_slot)?.is_none()
+            {
+                log!("Deactivated tables cannot be frozen");
+            } else {
+                log!("Deactivating tables cannot be frozen");
+            }
             return Err(ProgramError::InvalidArgument);
         }
         if data.len() <= LOOKUP_TABLE_META_SIZE || data[LOOKUP_TABLE_META_SIZE..].is_empty() {
@@ -140,6 +411,12 @@ pub fn process_freeze_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo]
 
     lookup_table_meta.authority_tag = 0;
     lookup_table_meta.authority = [0; 32];
+    lookup_table_meta.bump_version();
+    lookup_table_meta.write(unsafe { lookup_table_info.borrow_mut_data_unchecked() });
+
+    emit(&ProgramLog::Freeze {
+        table: *lookup_table_info.key(),
+    });
 
     Ok(())
 }
@@ -148,10 +425,24 @@ pub fn process_extend_lookup_table(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     new_addresses: &[u8],
+    expected_version: Option<u8>,
+    with_allowlist: bool,
 ) -> ProgramResult {
-    let [lookup_table_info, authority_info, payer_info, _system_program] = accounts else {
+    // Like the native program, the payer and system program may be left out
+    // when the table already holds enough lamports for its new size. In
+    // allowlist mode the allowlist comes right after the authority.
+    let [lookup_table_info, authority_info, remaining @ ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
+    let (allowlist_info, remaining) = if with_allowlist {
+        let [allowlist_info, remaining @ ..] = remaining else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        (Some(allowlist_info), remaining)
+    } else {
+        (None, remaining)
+    };
+    let payer_info = remaining.first();
 
     if lookup_table_info.owner() != program_id {
         log!("Lookup table owner should be the Address Lookup Table program");
@@ -163,27 +454,82 @@ pub fn process_extend_lookup_table(
         return Err(ProgramError::MissingRequiredSignature);
     }
 
+    // Only this program could sign for its own table, and it never does, so
+    // a table acting as its own authority can only come from a forged meta.
+    if lookup_table_info.key() == authority_info.key() {
+        log!("Lookup table cannot be its own authority");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if payer_info.is_some_and(|payer_info| lookup_table_info.key() == payer_info.key()) {
+        log!("Lookup table cannot pay for its own extension");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if allowlist_info.is_some_and(|allowlist_info| lookup_table_info.key() == allowlist_info.key())
+    {
+        log!("Lookup table cannot be its own allowlist");
+        return Err(ProgramError::InvalidArgument);
+    }
+
     let (new_addresses_start_index, new_table_data_len) = {
         let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
-        let meta = unsafe { &mut *(data.as_mut_ptr().add(4) as *mut LookupTableMeta) };
+        let mut meta = read_lookup_table_meta(data)?;
 
-        if meta.authority_tag == 0 {
+        if meta.is_frozen() {
             log!("Lookup table is already frozen");
             return Err(ProgramError::Immutable);
         }
 
-        if &meta.authority != authority_info.key() {
+        // A table bound to an allowlist has the allowlist as its authority,
+        // so it can only be extended through the allowlist's controller.
+        if let Some(allowlist_info) = allowlist_info {
+            if &meta.authority != allowlist_info.key() {
+                log!("Lookup table is not bound to this allowlist");
+                return Err(ProgramError::IncorrectAuthority);
+            }
+            let controller =
+                allowlist_controller(program_id, lookup_table_info.key(), allowlist_info)?;
+            if &controller != authority_info.key() {
+                log!("Incorrect lookup table authority");
+                return Err(ProgramError::IncorrectAuthority);
+            }
+        } else if &meta.authority != authority_info.key() {
             log!("Incorrect lookup table authority");
             return Err(ProgramError::IncorrectAuthority);
         }
 
-        if meta.deactivation_slot != Slot::MAX {
-            log!("Deactivated tables cannot be frozen");
+        if meta.is_deactivated() {
+            log!("Deactivated tables cannot be extended");
             return Err(ProgramError::InvalidArgument);
         }
 
-        let old_table_addresses_len = (data.len() - LOOKUP_TABLE_META_SIZE) / PUBKEY_BYTES;
+        if let Some(expected_version) = expected_version {
+            if meta.version == u8::MAX {
+                log!("Lookup table version is exhausted");
+                return Err(LookupTableError::VersionExhausted.into());
+            }
+            if meta.version != expected_version {
+                log!(
+                    "Lookup table version {} does not match expected version {}",
+                    meta.version,
+                    expected_version
+                );
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+
+        let old_table_addresses_bytes = data.len() - LOOKUP_TABLE_META_SIZE;
+        if !old_table_addresses_bytes.is_multiple_of(PUBKEY_BYTES) {
+            log!("Lookup table addresses are misaligned");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let old_table_addresses_len = old_table_addresses_bytes / PUBKEY_BYTES;
 
+        // The table state is checked before the payload, as in the native
+        // program, so an empty extend of a full table reports the table as
+        // full. Swapping the checks would change the error code clients see.
         if old_table_addresses_len >= LOOKUP_TABLE_MAX_ADDRESSES {
             log!("Lookup table is full and cannot contain more addresses");
             return Err(ProgramError::InvalidArgument);
@@ -206,11 +552,20 @@ pub fn process_extend_lookup_table(
             return Err(ProgramError::InvalidInstructionData);
         }
 
+        if let Some(allowlist_info) = allowlist_info {
+            check_allowlisted(allowlist_info, new_addresses)?;
+        }
+
         let clock = <Clock as Sysvar>::get()?;
         if clock.slot != meta.last_extended_slot {
             meta.last_extended_slot = clock.slot;
-            meta.last_extended_slot_start_index = old_table_addresses_len as u8;
+            // Bounded by `LOOKUP_TABLE_MAX_ADDRESSES` today; checked so that
+            // raising the cap can't silently truncate the index.
+            meta.last_extended_slot_start_index = u8::try_from(old_table_addresses_len)
+                .map_err(|_| ProgramError::ArithmeticOverflow)?;
         }
+        meta.bump_version();
+        meta.write(data);
 
         let new_table_data_len = LOOKUP_TABLE_META_SIZE
             .checked_add(new_table_addresses_len.saturating_mul(PUBKEY_BYTES))
@@ -227,9 +582,8 @@ pub fn process_extend_lookup_table(
 
     {
         let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
-        let offset = LOOKUP_TABLE_META_SIZE
-            .checked_add(new_addresses_start_index.saturating_mul(PUBKEY_BYTES))
-            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let offset =
+            address_offset(new_addresses_start_index).ok_or(ProgramError::InvalidArgument)?;
 
         if offset >= data.len() {
             return Err(ProgramError::InvalidArgument);
@@ -243,7 +597,13 @@ pub fn process_extend_lookup_table(
         .max(1)
         .saturating_sub(lookup_table_info.lamports());
 
+    let mut funded_by = None;
     if required_lamports > 0 {
+        let Some(payer_info) = payer_info else {
+            log!("Payer account is required to fund the extension");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
         if !payer_info.is_signer() {
             log!("Payer account must be a signer");
             return Err(ProgramError::MissingRequiredSignature);
@@ -255,16 +615,319 @@ pub fn process_extend_lookup_table(
             lamports: required_lamports,
         }
         .invoke()?;
+
+        funded_by = Some(*payer_info.key());
+    }
+
+    emit(&ProgramLog::Extend {
+        table: *lookup_table_info.key(),
+        new_count: (new_table_data_len - LOOKUP_TABLE_META_SIZE) / PUBKEY_BYTES,
+        payer: funded_by,
+    });
+
+    Ok(())
+}
+
+/// Checks that every new address is in the allowlist, whose addresses are
+/// sorted in ascending byte order by `ExtendAllowlist`. Addresses are found
+/// by binary search, so a full batch stays cheap for large allowlists. The
+/// caller checks that the allowlist is the one bound to the table.
+fn check_allowlisted(allowlist_info: &AccountInfo, new_addresses: &[u8]) -> ProgramResult {
+    let data = allowlist_info.try_borrow_data()?;
+    let addresses = data
+        .get(ALLOWLIST_HEADER_SIZE..)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    if !addresses.len().is_multiple_of(PUBKEY_BYTES) {
+        log!("Allowlist addresses are misaligned");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // SAFETY: `Pubkey` is a byte array, so any offset is aligned, and the
+    // length was checked to be a multiple of `PUBKEY_BYTES`.
+    let allowlist = unsafe {
+        core::slice::from_raw_parts(
+            addresses.as_ptr() as *const Pubkey,
+            addresses.len() / PUBKEY_BYTES,
+        )
+    };
+
+    for address in new_addresses.chunks_exact(PUBKEY_BYTES) {
+        if allowlist
+            .binary_search_by(|entry| entry.as_slice().cmp(address))
+            .is_err()
+        {
+            log!("Address is not in the allowlist");
+            if let Ok(address) = <&Pubkey>::try_from(address) {
+                pinocchio::pubkey::log(address);
+            }
+            return Err(LookupTableError::AddressNotAllowed.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the controller stored in the allowlist of `lookup_table`, after
+/// checking that the account is an allowlist this program created for that
+/// table. Whether the table is bound to it is up to the caller.
+fn allowlist_controller(
+    program_id: &Pubkey,
+    lookup_table: &Pubkey,
+    allowlist_info: &AccountInfo,
+) -> Result<Pubkey, ProgramError> {
+    if allowlist_info.owner() != program_id {
+        log!("Allowlist owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let data = allowlist_info.try_borrow_data()?;
+    if data.len() < ALLOWLIST_HEADER_SIZE {
+        log!("Allowlist account data is too small");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let bump_ref = [data[ALLOWLIST_BUMP_OFFSET]];
+    let derived_key = create_program_address(&allowlist_seeds(lookup_table, &bump_ref), program_id)
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+    if allowlist_info.key() != &derived_key {
+        log!("Allowlist address must match derived address");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut controller = [0; PUBKEY_BYTES];
+    controller.copy_from_slice(
+        &data[ALLOWLIST_CONTROLLER_OFFSET..ALLOWLIST_CONTROLLER_OFFSET + PUBKEY_BYTES],
+    );
+    Ok(controller)
+}
+
+/// Appends addresses to a table's allowlist, creating the allowlist and
+/// binding the table to it on first use. Binding makes the allowlist the
+/// table's authority and stores the previous authority in the allowlist as
+/// its controller. From then on the table is only extended by
+/// `ExtendLookupTableWithAllowlist` signed by the controller; every other
+/// authority instruction is refused until `ClearAllowlist` unbinds it.
+pub fn process_extend_allowlist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    bump_seed: u8,
+    new_addresses: &[u8],
+) -> ProgramResult {
+    let [lookup_table_info, authority_info, allowlist_info, payer_info, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if !authority_info.is_signer() {
+        log!("Authority account must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !payer_info.is_signer() {
+        log!("Payer account must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if payer_info.key() == allowlist_info.key() || payer_info.key() == lookup_table_info.key() {
+        log!("Allowlist cannot be paid for by itself or its table");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let bump_ref = [bump_seed];
+    let derived_key = create_program_address(
+        &allowlist_seeds(lookup_table_info.key(), &bump_ref),
+        program_id,
+    )
+    .map_err(|_| {
+        log!(
+            "Bump seed {} does not derive a valid allowlist address",
+            bump_seed
+        );
+        ProgramError::InvalidSeeds
+    })?;
+    if allowlist_info.key() != &derived_key {
+        log!("Allowlist address must match derived address");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let is_bound = {
+        let data = unsafe { lookup_table_info.borrow_data_unchecked() };
+        let meta = read_lookup_table_meta(data)?;
+
+        if meta.is_frozen() {
+            log!("Lookup table is already frozen");
+            return Err(ProgramError::Immutable);
+        }
+
+        let is_bound = &meta.authority == allowlist_info.key();
+        let authority = if is_bound {
+            allowlist_controller(program_id, lookup_table_info.key(), allowlist_info)?
+        } else {
+            meta.authority
+        };
+        if &authority != authority_info.key() {
+            log!("Incorrect lookup table authority");
+            return Err(ProgramError::IncorrectAuthority);
+        }
+
+        if meta.is_deactivated() {
+            log!("Deactivated tables cannot change their allowlist");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        is_bound
+    };
+
+    let old_addresses_len = if is_bound {
+        allowlist_info.data_len() - ALLOWLIST_HEADER_SIZE
+    } else {
+        if allowlist_info.owner() == program_id && !allowlist_info.data_is_empty() {
+            log!("Allowlist is already initialized");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        0
+    };
+
+    // Sorted and unique across calls, so lookups can binary search.
+    {
+        let data = allowlist_info.try_borrow_data()?;
+        let mut previous = data
+            .len()
+            .checked_sub(PUBKEY_BYTES)
+            .filter(|_| old_addresses_len > 0)
+            .map(|start| &data[start..]);
+        for address in new_addresses.chunks_exact(PUBKEY_BYTES) {
+            if previous.is_some_and(|previous| previous >= address) {
+                log!("Allowlist addresses must be sorted and unique");
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            previous = Some(address);
+        }
+    }
+
+    let new_data_len = ALLOWLIST_HEADER_SIZE
+        .checked_add(old_addresses_len)
+        .and_then(|len| len.checked_add(new_addresses.len()))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    if is_bound {
+        allowlist_info.resize(new_data_len)?;
+
+        let rent = <Rent as Sysvar>::get()?;
+        let required_lamports = rent
+            .minimum_balance(new_data_len)
+            .saturating_sub(allowlist_info.lamports());
+        if required_lamports > 0 {
+            instructions::Transfer {
+                from: payer_info,
+                to: allowlist_info,
+                lamports: required_lamports,
+            }
+            .invoke()?;
+        }
+    } else {
+        let seeds = [
+            Seed::from(ALLOWLIST_SEED),
+            Seed::from(lookup_table_info.key().as_ref()),
+            Seed::from(&bump_ref),
+        ];
+        create_program_account(
+            program_id,
+            allowlist_info,
+            payer_info,
+            new_data_len,
+            &[Signer::from(&seeds)],
+        )?;
+    }
+
+    {
+        let data = unsafe { allowlist_info.borrow_mut_data_unchecked() };
+        if !is_bound {
+            data[ALLOWLIST_CONTROLLER_OFFSET..ALLOWLIST_CONTROLLER_OFFSET + PUBKEY_BYTES]
+                .copy_from_slice(authority_info.key());
+            data[ALLOWLIST_BUMP_OFFSET] = bump_seed;
+        }
+        data[ALLOWLIST_HEADER_SIZE + old_addresses_len..].copy_from_slice(new_addresses);
+    }
+
+    if !is_bound {
+        let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
+        let mut meta = LookupTableMeta::read(data);
+        meta.authority = *allowlist_info.key();
+        meta.bump_version();
+        meta.write(data);
     }
 
     Ok(())
 }
 
+/// Unbinds a table from its allowlist: the controller becomes the table's
+/// authority again and the allowlist is closed, its lamports going to the
+/// recipient.
+pub fn process_clear_allowlist(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let [lookup_table_info, controller_info, allowlist_info, recipient_info, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if !controller_info.is_signer() {
+        log!("Authority account must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if recipient_info.key() == allowlist_info.key() {
+        log!("Allowlist cannot be the recipient of its own lamports");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut lookup_table_meta = {
+        let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
+        let meta = read_lookup_table_meta(data)?;
+
+        if &meta.authority != allowlist_info.key() {
+            log!("Lookup table is not bound to this allowlist");
+            return Err(ProgramError::IncorrectAuthority);
+        }
+
+        meta
+    };
+
+    let controller = allowlist_controller(program_id, lookup_table_info.key(), allowlist_info)?;
+    if &controller != controller_info.key() {
+        log!("Incorrect lookup table authority");
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    lookup_table_meta.authority = controller;
+    lookup_table_meta.bump_version();
+    lookup_table_meta.write(unsafe { lookup_table_info.borrow_mut_data_unchecked() });
+
+    let new_recipient_lamports = allowlist_info
+        .lamports()
+        .checked_add(recipient_info.lamports())
+        .ok_or::<ProgramError>(ProgramError::ArithmeticOverflow)?;
+
+    *recipient_info.try_borrow_mut_lamports()? = new_recipient_lamports;
+    allowlist_info.resize(0)?;
+    *allowlist_info.try_borrow_mut_lamports()? = 0;
+
+    Ok(())
+}
+
 pub fn process_deactivate_lookup_table(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
+    reason: u8,
 ) -> ProgramResult {
-    let [lookup_table_info, authority_info] = accounts else {
+    let [lookup_table_info, authority_info, ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
@@ -278,11 +941,11 @@ pub fn process_deactivate_lookup_table(
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let lookup_table_meta = {
+    let mut lookup_table_meta = {
         let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
-        let meta = unsafe { &mut *(data.as_mut_ptr().add(4) as *mut LookupTableMeta) };
+        let meta = read_lookup_table_meta(data)?;
 
-        if meta.authority_tag == 0 {
+        if meta.is_frozen() {
             log!("Lookup table is already frozen");
             return Err(ProgramError::Immutable);
         }
@@ -292,7 +955,7 @@ pub fn process_deactivate_lookup_table(
             return Err(ProgramError::IncorrectAuthority);
         }
 
-        if meta.deactivation_slot != Slot::MAX {
+        if meta.is_deactivated() {
             log!("Lookup table is already deactivated");
             return Err(ProgramError::InvalidArgument);
         }
@@ -302,12 +965,207 @@ pub fn process_deactivate_lookup_table(
 
     let clock = <Clock as Sysvar>::get()?;
     lookup_table_meta.deactivation_slot = clock.slot;
+    lookup_table_meta.deactivation_reason = reason;
+    lookup_table_meta.bump_version();
+    lookup_table_meta.write(unsafe { lookup_table_info.borrow_mut_data_unchecked() });
+
+    emit(&ProgramLog::Deactivate {
+        table: *lookup_table_info.key(),
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+/// Cancels a deactivation, making the table usable again. Works until the
+/// table is closed.
+pub fn process_reactivate_lookup_table(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let [lookup_table_info, authority_info, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if !authority_info.is_signer() {
+        log!("Authority account must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut lookup_table_meta = {
+        let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
+        let meta = read_lookup_table_meta(data)?;
+
+        if meta.is_frozen() {
+            log!("Lookup table is already frozen");
+            return Err(ProgramError::Immutable);
+        }
+
+        if &meta.authority != authority_info.key() {
+            log!("Incorrect lookup table authority");
+            return Err(ProgramError::IncorrectAuthority);
+        }
+
+        if meta.is_active() {
+            log!("Lookup table is not deactivated");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        meta
+    };
+
+    lookup_table_meta.deactivation_slot = Slot::MAX;
+    lookup_table_meta.deactivation_reason = 0;
+    lookup_table_meta.bump_version();
+    lookup_table_meta.write(unsafe { lookup_table_info.borrow_mut_data_unchecked() });
 
     Ok(())
 }
 
-pub fn process_close_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-    let [lookup_table_info, authority_info, recipient_info, slot_hashes_info] = accounts else {
+pub fn process_transfer_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let [lookup_table_info, authority_info, new_authority_info, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    if !authority_info.is_signer() {
+        log!("Authority account must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if lookup_table_info.key() == new_authority_info.key() {
+        log!("Lookup table cannot be its own authority");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut lookup_table_meta = {
+        let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
+        let meta = read_lookup_table_meta(data)?;
+
+        if meta.is_frozen() {
+            log!("Lookup table is already frozen");
+            return Err(ProgramError::Immutable);
+        }
+
+        if &meta.authority != authority_info.key() {
+            log!("Incorrect lookup table authority");
+            return Err(ProgramError::IncorrectAuthority);
+        }
+
+        if meta.is_deactivated() {
+            log!("Deactivated tables cannot change authority");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        meta
+    };
+
+    lookup_table_meta.authority = *new_authority_info.key();
+    lookup_table_meta.bump_version();
+    lookup_table_meta.write(unsafe { lookup_table_info.borrow_mut_data_unchecked() });
+
+    Ok(())
+}
+
+/// Tops a table up to the rent-exempt minimum for its current size, e.g.
+/// after a rent increase. Anyone can pay for it, the authority is not needed.
+pub fn process_top_up_rent(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let [lookup_table_info, payer_info, _system_program, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    read_lookup_table_meta(unsafe { lookup_table_info.borrow_data_unchecked() })?;
+
+    if lookup_table_info.key() == payer_info.key() {
+        log!("Lookup table cannot pay for its own rent");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let rent = <Rent as Sysvar>::get()?;
+    let required_lamports = rent
+        .minimum_balance(lookup_table_info.data_len())
+        .max(1)
+        .saturating_sub(lookup_table_info.lamports());
+
+    if required_lamports == 0 {
+        return Ok(());
+    }
+
+    if !payer_info.is_signer() {
+        log!("Payer account must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    instructions::Transfer {
+        from: payer_info,
+        to: lookup_table_info,
+        lamports: required_lamports,
+    }
+    .invoke()
+}
+
+/// Returns the table's rent standing against the current rent sysvar: its
+/// lamports, the rent-exempt minimum for its size and whether it meets it,
+/// laid out as described by [`RENT_STATUS_LEN`]. Lets a watchdog spot tables
+/// that a rent change left short before they become delinquent.
+///
+/// A closed table has no data and is refused like any other account that
+/// is not a table. Once the closing transaction ends, the system program
+/// owns it anyway.
+pub fn process_rent_status(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let [lookup_table_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    read_lookup_table_meta(unsafe { lookup_table_info.borrow_data_unchecked() })?;
+
+    let rent = <Rent as Sysvar>::get()?;
+    let current_lamports = lookup_table_info.lamports();
+    let required_lamports = rent.minimum_balance(lookup_table_info.data_len());
+
+    let mut return_data = [0u8; RENT_STATUS_LEN];
+    return_data[..8].copy_from_slice(&current_lamports.to_le_bytes());
+    return_data[8..16].copy_from_slice(&required_lamports.to_le_bytes());
+    return_data[16] = (current_lamports >= required_lamports) as u8;
+    set_return_data(&return_data);
+
+    Ok(())
+}
+
+/// Closes a deactivated table once its deactivation slot has left the slot
+/// hashes. The sysvar is searched whatever its length, so the cooldown
+/// follows the cluster's slot hashes capacity rather than `MAX_ENTRIES`.
+///
+/// Any account other than the table itself may receive the lamports,
+/// including the authority reclaiming its own table's rent. The runtime then
+/// hands over the same account in both roles, so it must be writable, and
+/// since the table is never the recipient the lamports always move between
+/// two distinct accounts.
+pub fn process_close_lookup_table(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    flags: u8,
+) -> ProgramResult {
+    let [lookup_table_info, authority_info, recipient_info, remaining @ ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
@@ -326,11 +1184,37 @@ pub fn process_close_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo])
         return Err(ProgramError::InvalidArgument);
     }
 
+    if !recipient_info.is_writable() {
+        log!("Recipient of reclaimed lamports must be writable");
+        return Err(ProgramError::Immutable);
+    }
+
+    if flags & CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT != 0 && recipient_info.owner() == program_id {
+        log!("Recipient of reclaimed lamports cannot be owned by the lookup table program");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if flags & CLOSE_BURN_LAMPORTS != 0 && recipient_info.key() != &INCINERATOR_ID {
+        log!("Recipient of burned lamports must be the incinerator");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // The slot hashes sysvar is the only optional account. Anything else in
+    // its place is rejected rather than ignored, so a client can't believe
+    // the table was checked against slot hashes it supplied itself.
+    let slot_hashes_info = match remaining.first() {
+        Some(info) if info.key() != &SLOTHASHES_ID => {
+            log!("Slot hashes account must be the slot hashes sysvar");
+            return Err(ProgramError::InvalidArgument);
+        }
+        slot_hashes_info => slot_hashes_info,
+    };
+
     {
         let data = unsafe { lookup_table_info.borrow_mut_data_unchecked() };
-        let meta = unsafe { &mut *(data.as_mut_ptr().add(4) as *mut LookupTableMeta) };
+        let meta = read_lookup_table_meta(data)?;
 
-        if meta.authority_tag == 0 {
+        if meta.is_frozen() {
             log!("Lookup table is frozen");
             return Err(ProgramError::Immutable);
         }
@@ -342,24 +1226,40 @@ pub fn process_close_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo])
         let clock = <Clock as Sysvar>::get()?;
         let current_slot = clock.slot;
 
-        // Want to avoid function call, they call a function in the reference
-
-        if meta.deactivation_slot == Slot::MAX {
+        if meta.is_active() {
             log!("Lookup table is not deactivated");
             return Err(ProgramError::InvalidArgument);
         } else if meta.deactivation_slot == current_slot {
             log!(
                 "Table cannot be closed until it's fully deactivated in {} blocks",
-                MAX_ENTRIES.saturating_add(1)
+                meta.deactivation_countdown(current_slot)
+                    .unwrap_or_default()
             );
             return Err(ProgramError::InvalidArgument);
         } else {
-            let slot_hashes = SlotHashes::from_account_info(slot_hashes_info)?;
-
-            if let Some(slot_position) = slot_hashes.position(meta.deactivation_slot) {
+            // The native program takes `[table, authority, recipient]` and
+            // reads the slot hashes through a syscall, the sysvar account is
+            // an optional extra here. The slot's age alone can't stand in for
+            // the lookup: the window is however many entries the cluster
+            // keeps, and skipped slots stretch it further back.
+            let (slot_position, slot_hashes_len) = match slot_hashes_info {
+                Some(slot_hashes_info) => {
+                    let slot_hashes = SlotHashes::from_account_info(slot_hashes_info)?;
+                    (
+                        slot_hashes.position(meta.deactivation_slot),
+                        slot_hashes.entries().len(),
+                    )
+                }
+                None => (
+                    slot_hashes_position(meta.deactivation_slot)?,
+                    slot_hashes_len()?,
+                ),
+            };
+
+            if let Some(slot_position) = slot_position {
                 log!(
                     "Table cannot be closed until it's fully deactivated in {} blocks",
-                    MAX_ENTRIES.saturating_sub(slot_position)
+                    slot_hashes_len.saturating_sub(slot_position)
                 );
                 return Err(ProgramError::InvalidArgument);
             }
@@ -371,18 +1271,128 @@ pub fn process_close_lookup_table(program_id: &Pubkey, accounts: &[AccountInfo])
         .checked_add(recipient_info.lamports())
         .ok_or::<ProgramError>(ProgramError::ArithmeticOverflow)?;
 
-    if !recipient_info.is_writable() {
+    if !lookup_table_info.is_writable() {
         return Err(ProgramError::Immutable);
     }
 
     *recipient_info.try_borrow_mut_lamports()? = new_recipient_lamports;
+    lookup_table_info.resize(0)?;
+    *lookup_table_info.try_borrow_mut_lamports()? = 0;
 
-    if !lookup_table_info.is_writable() {
-        return Err(ProgramError::Immutable);
+    emit(&ProgramLog::Close {
+        table: *lookup_table_info.key(),
+        recipient: *recipient_info.key(),
+    });
+
+    Ok(())
+}
+
+/// Returns, as a little endian `u64` in return data, how many addresses of
+/// the table can be used in the current slot. Addresses appended during the
+/// current slot are still warming up and are not counted. A deactivated
+/// table reports zero: it may still resolve during its cooldown, but no new
+/// transaction should be built against it.
+pub fn process_addresses_ready(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let [lookup_table_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
     }
 
-    lookup_table_info.resize(0)?;
-    *lookup_table_info.try_borrow_mut_lamports()? = 0;
+    let data = unsafe { lookup_table_info.borrow_data_unchecked() };
+    let meta = read_lookup_table_meta(data)?;
+
+    let ready_len = if meta.is_deactivated() {
+        0
+    } else {
+        let addresses_len = (data.len() - LOOKUP_TABLE_META_SIZE) / PUBKEY_BYTES;
+        let clock = <Clock as Sysvar>::get()?;
+        meta.active_addresses_len(clock.slot, addresses_len)
+            .min(addresses_len) as u64
+    };
+
+    set_return_data(&ready_len.to_le_bytes());
+
+    Ok(())
+}
+
+/// Returns, as a little endian `u64` in return data, how many slots ago the
+/// table was last extended, e.g. to find stale tables. A table that was
+/// never extended has a `last_extended_slot` of zero and so reports the
+/// current slot; its address count tells the two cases apart.
+pub fn process_get_age(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let [lookup_table_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if lookup_table_info.owner() != program_id {
+        log!("Lookup table owner should be the Address Lookup Table program");
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let data = unsafe { lookup_table_info.borrow_data_unchecked() };
+    let meta = read_lookup_table_meta(data)?;
+
+    let clock = <Clock as Sysvar>::get()?;
+    let age = clock.slot.saturating_sub(meta.last_extended_slot);
+
+    set_return_data(&age.to_le_bytes());
+
+    Ok(())
+}
+
+/// Returns the crate version and the supported features as
+/// [`ProgramVersion`] bytes. Takes no accounts, so it can be simulated
+/// against any cluster.
+pub fn process_get_version() -> ProgramResult {
+    set_return_data(&ProgramVersion::CURRENT.to_bytes());
+
+    Ok(())
+}
+
+pub fn process_collect_addresses(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    if accounts.is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let mut collected = [0u8; MAX_COLLECTED_ADDRESSES * PUBKEY_BYTES];
+    let mut collected_len = 0;
+
+    for lookup_table_info in accounts {
+        if lookup_table_info.owner() != program_id {
+            log!("Lookup table owner should be the Address Lookup Table program");
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let data = unsafe { lookup_table_info.borrow_data_unchecked() };
+        read_lookup_table_meta(data)?;
+
+        for address in data[LOOKUP_TABLE_META_SIZE..].chunks_exact(PUBKEY_BYTES) {
+            let collected_bytes = collected_len * PUBKEY_BYTES;
+            if collected[..collected_bytes]
+                .chunks_exact(PUBKEY_BYTES)
+                .any(|existing| existing == address)
+            {
+                continue;
+            }
+
+            if collected_len == MAX_COLLECTED_ADDRESSES {
+                log!(
+                    "Collected addresses would exceed the return data capacity of {}",
+                    MAX_COLLECTED_ADDRESSES
+                );
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            collected[collected_bytes..collected_bytes + PUBKEY_BYTES].copy_from_slice(address);
+            collected_len += 1;
+        }
+    }
+
+    set_return_data(&collected[..collected_len * PUBKEY_BYTES]);
 
     Ok(())
 }
@@ -0,0 +1,120 @@
+//! Reading lookup tables from RPC `getAccountInfo` responses, for indexers
+//! that receive the account owner and base64 encoded data rather than raw
+//! account bytes.
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "rpc")]
+pub mod client;
+
+use crate::interface::ProgramVersion;
+use crate::state::{LookupTable, LookupTableMeta, Pubkey};
+use crate::{check_id, CANONICAL_LOOKUP_TABLE_PROGRAM_ID};
+
+/// A lookup table decoded into owned memory.
+#[derive(Debug)]
+pub struct AddressLookupTableOwned {
+    pub meta: LookupTableMeta,
+    pub addresses: Vec<Pubkey>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The account is not owned by the Address Lookup Table program.
+    InvalidOwner,
+    /// The data is not valid standard, padded base64.
+    InvalidBase64,
+    /// The data decoded, but is not an initialized lookup table.
+    InvalidAccountData,
+    /// The return data was written by another program.
+    InvalidProgramId,
+    /// The return data decoded, but is not a `GetVersion` result.
+    InvalidReturnData,
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            ParseError::InvalidOwner => "account is not owned by the address lookup table program",
+            ParseError::InvalidBase64 => "account data is not valid base64",
+            ParseError::InvalidAccountData => "account data is not a lookup table",
+            ParseError::InvalidProgramId => {
+                "return data is not from the address lookup table program"
+            }
+            ParseError::InvalidReturnData => "return data is not a program version",
+        })
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a table from the `owner` and `data` fields of a `getAccountInfo`
+/// response requested with `"encoding": "base64"`. The owner may be either
+/// the native program or the id this crate is built for.
+///
+/// The RPC's base58 encoding is not supported: it is refused for accounts
+/// larger than 128 bytes, which covers any table holding more than two
+/// addresses.
+pub fn from_rpc_account(
+    owner: &Pubkey,
+    data_base64: &str,
+) -> Result<AddressLookupTableOwned, ParseError> {
+    if !check_id(owner) && owner != &CANONICAL_LOOKUP_TABLE_PROGRAM_ID {
+        return Err(ParseError::InvalidOwner);
+    }
+
+    let data = decode_base64(data_base64).ok_or(ParseError::InvalidBase64)?;
+    let table = LookupTable::from_bytes(&data).map_err(|_| ParseError::InvalidAccountData)?;
+
+    Ok(AddressLookupTableOwned {
+        meta: table.meta(),
+        addresses: table.addresses().to_vec(),
+    })
+}
+
+/// Decodes the result of simulating `GetVersion`, from the `programId` and
+/// the base64 `data` of the `simulateTransaction` response's `returnData`.
+/// As with [`from_rpc_account`], the program may be either the native id or
+/// the one this crate is built for.
+pub fn query_version(program_id: &Pubkey, data_base64: &str) -> Result<ProgramVersion, ParseError> {
+    if !check_id(program_id) && program_id != &CANONICAL_LOOKUP_TABLE_PROGRAM_ID {
+        return Err(ParseError::InvalidProgramId);
+    }
+
+    let data = decode_base64(data_base64).ok_or(ParseError::InvalidBase64)?;
+    ProgramVersion::from_return_data(&data).ok_or(ParseError::InvalidReturnData)
+}
+
+/// Decodes standard base64 with `=` padding, as RPC nodes return it.
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+    if !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let padding = encoded.iter().rev().take_while(|c| **c == b'=').count();
+    if padding > 2 || encoded[..encoded.len() - padding].contains(&b'=') {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    for chunk in encoded.chunks_exact(4) {
+        let mut group = 0u32;
+        for character in chunk {
+            let value = match character {
+                b'A'..=b'Z' => character - b'A',
+                b'a'..=b'z' => character - b'a' + 26,
+                b'0'..=b'9' => character - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                b'=' => 0,
+                _ => return None,
+            };
+            group = group << 6 | value as u32;
+        }
+        decoded.extend_from_slice(&group.to_be_bytes()[1..]);
+    }
+    decoded.truncate(decoded.len() - padding);
+
+    Some(decoded)
+}
@@ -0,0 +1,175 @@
+//! Async helpers for scripts and keepers that talk to an RPC node. The
+//! JSON-RPC transport is left to the caller, e.g. a thin wrapper around an
+//! HTTP client, so this crate pulls in neither an HTTP stack nor a runtime.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::{from_rpc_account, AddressLookupTableOwned, ParseError};
+use crate::base58::{decode_32, Base58};
+use crate::interface::{AUTHORITY_OFFSET, LOOKUP_TABLE_DISCRIMINATOR};
+use crate::state::{LookupTableStatus, Pubkey};
+use crate::LOOKUP_TABLE_PROGRAM_ID;
+
+const SLOT_HASHES_SYSVAR_ID: &str = "SysvarS1otHashes111111111111111111111111111";
+
+/// Sends JSON-RPC requests to a node.
+pub trait RpcTransport {
+    /// Calls `method` with `params` and returns the response's `result`.
+    fn request(&self, method: &str, params: Value)
+        -> impl Future<Output = Result<Value, RpcError>>;
+
+    /// Waits `duration`, between the polls of [`await_deactivation_cooldown`].
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RpcError {
+    /// The transport failed or the node returned a JSON-RPC error.
+    Transport(String),
+    /// The response is missing a field or has one of the wrong type.
+    InvalidResponse,
+    /// There is no account at the requested address.
+    AccountNotFound,
+    /// The account exists but is not a lookup table.
+    Parse(ParseError),
+    /// The table is active, so it has no cooldown to wait for.
+    NotDeactivated,
+}
+
+impl core::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RpcError::Transport(message) => write!(f, "rpc request failed: {message}"),
+            RpcError::InvalidResponse => f.write_str("unexpected rpc response"),
+            RpcError::AccountNotFound => f.write_str("account not found"),
+            RpcError::Parse(error) => write!(f, "{error}"),
+            RpcError::NotDeactivated => f.write_str("lookup table is not deactivated"),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl From<ParseError> for RpcError {
+    fn from(error: ParseError) -> Self {
+        RpcError::Parse(error)
+    }
+}
+
+/// Fetches and decodes the table at `table`.
+pub async fn fetch_lookup_table<T: RpcTransport>(
+    rpc: &T,
+    table: &Pubkey,
+) -> Result<AddressLookupTableOwned, RpcError> {
+    let (_, account) = get_account(rpc, &base58(table)).await?;
+    let (owner, data) = owner_and_data(&account)?;
+    Ok(from_rpc_account(&owner, data)?)
+}
+
+/// Fetches every table of this program whose authority is `authority`.
+/// Frozen tables have no authority and are never returned.
+pub async fn fetch_tables_for_authority<T: RpcTransport>(
+    rpc: &T,
+    authority: &Pubkey,
+) -> Result<Vec<(Pubkey, AddressLookupTableOwned)>, RpcError> {
+    // The offsets come from the layout constants, so the node filters on
+    // the same bytes the program writes.
+    let discriminator = base58(&LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes());
+    let params = json!([
+        base58(&LOOKUP_TABLE_PROGRAM_ID),
+        {
+            "encoding": "base64",
+            "filters": [
+                { "memcmp": { "offset": 0, "bytes": discriminator } },
+                { "memcmp": { "offset": AUTHORITY_OFFSET, "bytes": base58(authority) } },
+            ],
+        },
+    ]);
+    let accounts = rpc.request("getProgramAccounts", params).await?;
+
+    accounts
+        .as_array()
+        .ok_or(RpcError::InvalidResponse)?
+        .iter()
+        .map(|keyed_account| {
+            let key = keyed_account["pubkey"]
+                .as_str()
+                .and_then(decode_32)
+                .ok_or(RpcError::InvalidResponse)?;
+            let (owner, data) = owner_and_data(&keyed_account["account"])?;
+            Ok((key, from_rpc_account(&owner, data)?))
+        })
+        .collect()
+}
+
+/// Polls every `poll_interval` until the deactivated table at `table` can
+/// be closed, i.e. its deactivation slot has left the slot hashes.
+pub async fn await_deactivation_cooldown<T: RpcTransport>(
+    rpc: &T,
+    table: &Pubkey,
+    poll_interval: Duration,
+) -> Result<(), RpcError> {
+    let table = base58(table);
+
+    loop {
+        let (_, account) = get_account(rpc, &table).await?;
+        let (owner, data) = owner_and_data(&account)?;
+        let meta = from_rpc_account(&owner, data)?.meta;
+        if meta.is_active() {
+            return Err(RpcError::NotDeactivated);
+        }
+
+        let (current_slot, slot_hashes) = get_account(rpc, SLOT_HASHES_SYSVAR_ID).await?;
+        let slot_hashes = slot_hashes["data"][0]
+            .as_str()
+            .and_then(super::decode_base64)
+            .ok_or(RpcError::InvalidResponse)?;
+
+        if meta.status(current_slot, &slot_hashes) == LookupTableStatus::Deactivated {
+            return Ok(());
+        }
+
+        rpc.sleep(poll_interval).await;
+    }
+}
+
+/// `getAccountInfo` with base64 data, returning the context slot and the
+/// account.
+async fn get_account<T: RpcTransport>(rpc: &T, key: &str) -> Result<(u64, Value), RpcError> {
+    let mut response = rpc
+        .request("getAccountInfo", json!([key, { "encoding": "base64" }]))
+        .await?;
+
+    let slot = response["context"]["slot"]
+        .as_u64()
+        .ok_or(RpcError::InvalidResponse)?;
+    match response
+        .get_mut("value")
+        .map(Value::take)
+        .ok_or(RpcError::InvalidResponse)?
+    {
+        Value::Null => Err(RpcError::AccountNotFound),
+        account => Ok((slot, account)),
+    }
+}
+
+fn owner_and_data(account: &Value) -> Result<(Pubkey, &str), RpcError> {
+    let owner = account["owner"]
+        .as_str()
+        .and_then(decode_32)
+        .ok_or(RpcError::InvalidResponse)?;
+    let data = account["data"][0]
+        .as_str()
+        .ok_or(RpcError::InvalidResponse)?;
+    Ok((owner, data))
+}
+
+fn base58(bytes: &[u8]) -> String {
+    format!("{:?}", Base58(bytes))
+}
@@ -1,9 +1,52 @@
-use pinocchio::program_error::ProgramError;
-use pinocchio::pubkey::Pubkey;
+//! Account layout and parsing. Nothing here depends on pinocchio, so the
+//! module also builds without the `program` feature, e.g. for wasm explorers
+//! decoding tables client-side.
 
-pub const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
-pub const LOOKUP_TABLE_META_SIZE: usize = 56;
+#[cfg(feature = "client")]
+use crate::base58::Base58;
+use crate::interface::{
+    address_offset, AUTHORITY_OFFSET, AUTHORITY_TAG_OFFSET, DEACTIVATION_REASON_OFFSET,
+    DEACTIVATION_SLOT_OFFSET, LAST_EXTENDED_SLOT_OFFSET, LAST_EXTENDED_SLOT_START_INDEX_OFFSET,
+    LOOKUP_TABLE_DISCRIMINATOR, PUBKEY_BYTES, VERSION_OFFSET,
+};
+pub use crate::interface::{
+    lookup_table_seeds, LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE,
+};
 
+/// Same as `pinocchio::pubkey::Pubkey`, declared here so parsing does not
+/// need pinocchio.
+pub type Pubkey = [u8; PUBKEY_BYTES];
+pub type Slot = u64;
+
+/// Entries kept in the slot hashes sysvar.
+const MAX_ENTRIES: usize = 512;
+/// Largest return data a program can set.
+const MAX_RETURN_DATA: usize = 1024;
+
+#[cfg(feature = "program")]
+const _: () = {
+    assert!(PUBKEY_BYTES == pinocchio::pubkey::PUBKEY_BYTES);
+    assert!(MAX_ENTRIES == pinocchio::sysvars::slot_hashes::MAX_ENTRIES);
+    assert!(MAX_RETURN_DATA == pinocchio::cpi::MAX_RETURN_DATA);
+};
+
+/// The errors the parsing code returns, the same with or without the
+/// `program` feature. Each converts into the pinocchio `ProgramError` variant
+/// of the same name, so `?` works in programs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    InvalidArgument,
+    InvalidAccountData,
+}
+
+/// Maximum number of addresses `CollectAddresses` can fit in return data.
+pub const MAX_COLLECTED_ADDRESSES: usize = MAX_RETURN_DATA / PUBKEY_BYTES;
+
+/// Both padding bytes of the native layout are taken by `version` and
+/// `deactivation_reason`, so any further per-table field would change the
+/// account size and break parsers written against the native layout. Every
+/// create path derives the table address from `[authority, recent_slot]`,
+/// so there is no creation kind to record either.
 #[repr(C)]
 pub struct LookupTableMeta {
     pub deactivation_slot: u64,
@@ -11,26 +54,349 @@ pub struct LookupTableMeta {
     pub last_extended_slot_start_index: u8,
     pub authority_tag: u8,
     pub authority: Pubkey,
-    pub _padding: u16,
+    /// Bumped on every mutation so clients can detect concurrent changes.
+    /// Only the two padding bytes of the native layout are free, so the
+    /// counter is a `u8`. Rather than wrap, it stays at `u8::MAX`, where
+    /// `ExtendLookupTableChecked` can no longer be used but every other
+    /// instruction still is.
+    pub version: u8,
+    /// Caller-defined tag for why the table was deactivated, e.g. manual,
+    /// migration or expiry. Zero when none was given or while active.
+    pub deactivation_reason: u8,
+}
+
+impl LookupTableMeta {
+    /// Decodes the meta from a table's account `data`, field by field at
+    /// the `interface` offsets, so the data needs no particular alignment.
+    /// Panics if `data` is shorter than [`LOOKUP_TABLE_META_SIZE`].
+    pub fn read(data: &[u8]) -> Self {
+        let u64_at = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let mut authority = [0u8; PUBKEY_BYTES];
+        authority.copy_from_slice(&data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + PUBKEY_BYTES]);
+
+        Self {
+            deactivation_slot: u64_at(DEACTIVATION_SLOT_OFFSET),
+            last_extended_slot: u64_at(LAST_EXTENDED_SLOT_OFFSET),
+            last_extended_slot_start_index: data[LAST_EXTENDED_SLOT_START_INDEX_OFFSET],
+            authority_tag: data[AUTHORITY_TAG_OFFSET],
+            authority,
+            version: data[VERSION_OFFSET],
+            deactivation_reason: data[DEACTIVATION_REASON_OFFSET],
+        }
+    }
+
+    /// Encodes the meta into a table's account `data`, the counterpart of
+    /// [`LookupTableMeta::read`]. The discriminator and the addresses are
+    /// left untouched. Panics if `data` is shorter than
+    /// [`LOOKUP_TABLE_META_SIZE`].
+    pub fn write(&self, data: &mut [u8]) {
+        data[DEACTIVATION_SLOT_OFFSET..DEACTIVATION_SLOT_OFFSET + 8]
+            .copy_from_slice(&self.deactivation_slot.to_le_bytes());
+        data[LAST_EXTENDED_SLOT_OFFSET..LAST_EXTENDED_SLOT_OFFSET + 8]
+            .copy_from_slice(&self.last_extended_slot.to_le_bytes());
+        data[LAST_EXTENDED_SLOT_START_INDEX_OFFSET] = self.last_extended_slot_start_index;
+        data[AUTHORITY_TAG_OFFSET] = self.authority_tag;
+        data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + PUBKEY_BYTES].copy_from_slice(&self.authority);
+        data[VERSION_OFFSET] = self.version;
+        data[DEACTIVATION_REASON_OFFSET] = self.deactivation_reason;
+    }
+
+    /// Whether the authority was dropped, after which the table can never
+    /// change again.
+    #[inline]
+    #[must_use]
+    pub fn is_frozen(&self) -> bool {
+        self.authority_tag == 0
+    }
+
+    /// Whether the table was deactivated, whether or not its cooldown is
+    /// over. See [`LookupTableMeta::status`] for the cooldown.
+    #[inline]
+    #[must_use]
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivation_slot != Slot::MAX
+    }
+
+    /// Whether the table was never deactivated, or was reactivated since.
+    #[inline]
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        !self.is_deactivated()
+    }
+
+    /// Number of addresses that can be used at `current_slot`. Addresses
+    /// appended during the current slot only become active in the next one.
+    #[inline]
+    pub fn active_addresses_len(&self, current_slot: Slot, addresses_len: usize) -> usize {
+        if current_slot > self.last_extended_slot {
+            addresses_len
+        } else {
+            self.last_extended_slot_start_index as usize
+        }
+    }
+
+    /// Estimated slots left until a deactivated table can be closed, or
+    /// `None` while it is still active. This is only an estimate: it assumes
+    /// every slot produced a block, so with skipped slots the table becomes
+    /// closeable later than this says, by as many slots as were skipped.
+    /// Neither close nor the runtime use it. For their answer, use
+    /// [`LookupTableMeta::status`] with the slot hashes.
+    #[inline]
+    pub fn deactivation_countdown(&self, current_slot: Slot) -> Option<u64> {
+        if self.is_active() {
+            return None;
+        }
+
+        let elapsed = current_slot.saturating_sub(self.deactivation_slot);
+        Some((MAX_ENTRIES as u64 + 1).saturating_sub(elapsed))
+    }
+
+    /// The raw addresses appended in `last_extended_slot`, from the table's
+    /// account `data`. These are the ones not yet usable in that slot.
+    pub fn last_extended_slot_addresses<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], StateError> {
+        address_offset(self.last_extended_slot_start_index as usize)
+            .and_then(|start| data.get(start..))
+            .ok_or(StateError::InvalidAccountData)
+    }
+
+    /// Counts a mutation. The version saturates at `u8::MAX` so that an old
+    /// version can never match again.
+    #[inline]
+    pub fn bump_version(&mut self) {
+        self.version = self.version.saturating_add(1);
+    }
+
+    /// Status at `current_slot`, following the runtime's rules: a deactivated
+    /// table stays usable while its deactivation slot is in `slot_hashes`,
+    /// the raw slot hashes sysvar data.
+    pub fn status(&self, current_slot: Slot, slot_hashes: &[u8]) -> LookupTableStatus {
+        if self.is_active() {
+            LookupTableStatus::Activated
+        } else if self.deactivation_slot == current_slot {
+            LookupTableStatus::Deactivating {
+                remaining_blocks: MAX_ENTRIES + 1,
+            }
+        } else if let Some(position) =
+            slot_hashes_data_position(slot_hashes, self.deactivation_slot)
+        {
+            LookupTableStatus::Deactivating {
+                remaining_blocks: MAX_ENTRIES.saturating_sub(position),
+            }
+        } else {
+            LookupTableStatus::Deactivated
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LookupTableStatus {
+    Activated,
+    Deactivating { remaining_blocks: usize },
+    Deactivated,
+}
+
+/// Position of `slot` in raw slot hashes sysvar data: a little endian `u64`
+/// count followed by `(slot, hash)` entries, most recent slot first.
+fn slot_hashes_data_position(data: &[u8], slot: Slot) -> Option<usize> {
+    const ENTRY_SIZE: usize = 8 + 32;
+
+    let len = u64::from_le_bytes(data.get(0..8)?.try_into().ok()?) as usize;
+    let entries = &data[8..];
+    let slot_at = |index: usize| {
+        let offset = index * ENTRY_SIZE;
+        u64::from_le_bytes(entries[offset..offset + 8].try_into().unwrap())
+    };
+
+    // Slots are descending, so a larger slot means the target is further on.
+    let (mut low, mut high) = (0, len.min(entries.len() / ENTRY_SIZE));
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match slot_at(mid).cmp(&slot) {
+            core::cmp::Ordering::Equal => return Some(mid),
+            core::cmp::Ordering::Greater => low = mid + 1,
+            core::cmp::Ordering::Less => high = mid,
+        }
+    }
+    None
 }
 
+// `LookupTableMeta` is written in place after the 4-byte discriminator, so
+// its fields must sit at the offsets clients read them from, and together
+// fill exactly `LOOKUP_TABLE_META_SIZE` bytes.
+const _: () = {
+    use core::mem::offset_of;
+
+    assert!(4 + offset_of!(LookupTableMeta, deactivation_slot) == DEACTIVATION_SLOT_OFFSET);
+    assert!(4 + offset_of!(LookupTableMeta, last_extended_slot) == LAST_EXTENDED_SLOT_OFFSET);
+    assert!(
+        4 + offset_of!(LookupTableMeta, last_extended_slot_start_index)
+            == LAST_EXTENDED_SLOT_START_INDEX_OFFSET
+    );
+    assert!(4 + offset_of!(LookupTableMeta, authority_tag) == AUTHORITY_TAG_OFFSET);
+    assert!(4 + offset_of!(LookupTableMeta, authority) == AUTHORITY_OFFSET);
+    assert!(4 + offset_of!(LookupTableMeta, version) == VERSION_OFFSET);
+    assert!(4 + offset_of!(LookupTableMeta, deactivation_reason) == DEACTIVATION_REASON_OFFSET);
+    assert!(DEACTIVATION_REASON_OFFSET + core::mem::size_of::<u8>() == LOOKUP_TABLE_META_SIZE);
+};
+
+/// Writes an empty, active table for `authority_key` into `data`. Anything
+/// past the meta is zeroed, so a buffer that held a table before, e.g. one
+/// closed and recreated in the same transaction, keeps no stale addresses.
 #[inline]
 pub fn serialize_new_lookup_table(
     data: &mut [u8],
     authority_key: &Pubkey,
-) -> Result<(), ProgramError> {
-    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+) -> Result<(), StateError> {
+    if data.len() < LOOKUP_TABLE_META_SIZE {
+        return Err(StateError::InvalidAccountData);
+    }
+    data[LOOKUP_TABLE_META_SIZE..].fill(0);
+
+    data[0..4].copy_from_slice(&LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes());
+
+    LookupTableMeta {
+        deactivation_slot: u64::MAX,
+        last_extended_slot: 0,
+        last_extended_slot_start_index: 0,
+        authority_tag: 1,
+        authority: *authority_key,
+        version: 0,
+        deactivation_reason: 0,
+    }
+    .write(data);
 
-    let meta = unsafe { &mut *(data.as_mut_ptr().add(4) as *mut LookupTableMeta) };
+    Ok(())
+}
+
+/// Zero-copy view of a lookup table account. The meta fields are decoded
+/// from their byte offsets, so the account data needs no particular
+/// alignment, and the addresses are borrowed in place.
+pub struct LookupTable<'a> {
+    data: &'a [u8],
+}
 
-    meta.deactivation_slot = u64::MAX;
-    meta.last_extended_slot = 0;
-    meta.last_extended_slot_start_index = 0;
+impl<'a> LookupTable<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, StateError> {
+        if data.len() < LOOKUP_TABLE_META_SIZE
+            || data[0..4] != LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes()
+        {
+            return Err(StateError::InvalidAccountData);
+        }
+        if !(data.len() - LOOKUP_TABLE_META_SIZE).is_multiple_of(PUBKEY_BYTES) {
+            return Err(StateError::InvalidAccountData);
+        }
 
-    meta.authority_tag = 1;
-    meta.authority = *authority_key;
+        Ok(Self { data })
+    }
 
-    meta._padding = 0;
+    pub fn meta(&self) -> LookupTableMeta {
+        LookupTableMeta::read(self.data)
+    }
 
-    Ok(())
+    /// The authority, or `None` once the table is frozen.
+    pub fn authority(&self) -> Option<&'a Pubkey> {
+        let data: &'a [u8] = self.data;
+        (data[AUTHORITY_TAG_OFFSET] != 0)
+            .then(|| unsafe { &*(data[AUTHORITY_OFFSET..].as_ptr() as *const Pubkey) })
+    }
+
+    pub fn addresses(&self) -> &'a [Pubkey] {
+        let bytes: &'a [u8] = &self.data[LOOKUP_TABLE_META_SIZE..];
+        // SAFETY: `Pubkey` is a byte array, so any offset is aligned, and the
+        // length was checked to be a multiple of `PUBKEY_BYTES`.
+        unsafe {
+            core::slice::from_raw_parts(bytes.as_ptr() as *const Pubkey, bytes.len() / PUBKEY_BYTES)
+        }
+    }
+
+    /// Iterates the stored addresses in index order, whether or not they
+    /// are active yet.
+    pub fn iter_addresses(&self) -> core::slice::Iter<'a, Pubkey> {
+        self.addresses().iter()
+    }
+
+    /// Number of addresses that can be used at `current_slot`.
+    pub fn get_active_addresses_len(&self, current_slot: Slot) -> usize {
+        self.active_addresses(current_slot).len()
+    }
+
+    /// The addresses that can be used at `current_slot`. The start index of
+    /// the last extension is read from the account data, so it is capped at
+    /// the number of stored addresses rather than trusted.
+    pub fn active_addresses(&self, current_slot: Slot) -> &'a [Pubkey] {
+        let addresses = self.addresses();
+        let len = self
+            .meta()
+            .active_addresses_len(current_slot, addresses.len())
+            .min(addresses.len());
+        &addresses[..len]
+    }
+
+    /// Resolves `indexes` into `out`, which must have the same length. Fails
+    /// if an index is not active at `current_slot`, or if the table has been
+    /// deactivated for longer than the slot hashes window and can no longer
+    /// be used.
+    ///
+    /// That window is only an estimate here: without the slot hashes it
+    /// comes from [`LookupTableMeta::deactivation_countdown`], which ignores
+    /// skipped slots. It can reject a table the runtime still resolves, but
+    /// never accepts one the runtime refuses. For the runtime's answer, check
+    /// [`LookupTableMeta::status`] with the slot hashes, or resolve with
+    /// `message::resolve_lookup`.
+    pub fn lookup(
+        &self,
+        current_slot: Slot,
+        indexes: &[u8],
+        out: &mut [Pubkey],
+    ) -> Result<(), StateError> {
+        if indexes.len() != out.len() {
+            return Err(StateError::InvalidArgument);
+        }
+
+        let meta = self.meta();
+        if meta.deactivation_countdown(current_slot) == Some(0) {
+            return Err(StateError::InvalidArgument);
+        }
+
+        let active_addresses = self.active_addresses(current_slot);
+        for (index, address) in indexes.iter().zip(out.iter_mut()) {
+            *address = *active_addresses
+                .get(*index as usize)
+                .ok_or(StateError::InvalidArgument)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client")]
+impl core::fmt::Debug for LookupTableMeta {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut debug = f.debug_struct("LookupTableMeta");
+
+        if self.is_frozen() {
+            debug.field("authority", &format_args!("<frozen>"));
+        } else {
+            debug.field("authority", &Base58(&self.authority));
+        }
+
+        debug
+            .field("frozen", &self.is_frozen())
+            .field(
+                "deactivation_slot",
+                &(self.deactivation_slot != u64::MAX).then_some(self.deactivation_slot),
+            )
+            .field("last_extended_slot", &self.last_extended_slot)
+            .field(
+                "last_extended_slot_start_index",
+                &self.last_extended_slot_start_index,
+            )
+            .field("version", &self.version)
+            .field("deactivation_reason", &self.deactivation_reason)
+            .finish()
+    }
 }
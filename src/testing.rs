@@ -0,0 +1,23 @@
+//! Helpers for downstream test suites that need this program at the
+//! canonical address, e.g. to exercise v0 transactions. The binary is the
+//! one at `LOOKUP_TABLE_PROGRAM_ELF` when building, by default the one
+//! `cargo build-sbf` writes to `tests/fixtures`.
+
+#[cfg(feature = "mollusk")]
+use crate::LOOKUP_TABLE_PROGRAM_ID;
+
+/// The program binary, embedded at build time.
+pub fn program_elf() -> &'static [u8] {
+    include_bytes!(env!("LOOKUP_TABLE_PROGRAM_ELF"))
+}
+
+/// Deploys the program to `mollusk` at [`LOOKUP_TABLE_PROGRAM_ID`], in place
+/// of the builtin lookup table program.
+#[cfg(feature = "mollusk")]
+pub fn mollusk_add_program(mollusk: &mut mollusk_svm::Mollusk) {
+    mollusk.add_program_with_elf_and_loader(
+        &LOOKUP_TABLE_PROGRAM_ID.into(),
+        program_elf(),
+        &mollusk_svm::program::loader_keys::LOADER_V3,
+    );
+}
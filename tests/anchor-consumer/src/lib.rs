@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use p_address_lookup_table::anchor::{cpi, CreateLookupTable};
+
+anchor_lang::solana_program::entrypoint!(process_instruction);
+
+fn process_instruction<'info>(
+    _program_id: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+    data: &[u8],
+) -> ProgramResult {
+    let [program, lookup_table, authority, payer, system_program, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let [recent_slot @ .., bump_seed] = data else {
+        return Err(ProgramError::InvalidInstructionData);
+    };
+    let recent_slot = u64::from_le_bytes(
+        recent_slot
+            .try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?,
+    );
+
+    let ctx = CpiContext::new(
+        program.clone(),
+        CreateLookupTable {
+            lookup_table: lookup_table.clone(),
+            authority: authority.clone(),
+            payer: payer.clone(),
+            system_program: system_program.clone(),
+        },
+    );
+    cpi::create_lookup_table(ctx, recent_slot, *bump_seed).map_err(Into::into)
+}
@@ -0,0 +1,174 @@
+//! When a deactivated table may be closed, as a table of slot arithmetic
+//! cases. Each case states the expected outcome and is also checked against
+//! the reference `LookupTableMeta::status` of
+//! `solana-address-lookup-table-interface`: a table is closeable exactly when
+//! the reference reports it `Deactivated`. Validators resolving old
+//! transactions rely on tables not disappearing before that.
+
+mod common;
+
+use common::{
+    close_instruction, make_lookup_table_account, populated_slot_hashes, AUTHORITY,
+    PROGRAM_FILE_NAME, PROGRAM_ID, SLOT_HASHES_MAX_ENTRIES,
+};
+use mollusk_svm::{result::ProgramResult, Mollusk};
+use solana_account::Account;
+use solana_address_lookup_table_interface::state as upstream_state;
+use solana_program::{hash::Hash, slot_hashes::SlotHashes};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// The slot hashes at `current_slot` when every earlier slot produced a
+/// block: the previous `SLOT_HASHES_MAX_ENTRIES` slots, most recent first.
+fn window(current_slot: u64) -> Vec<u64> {
+    (current_slot.saturating_sub(SLOT_HASHES_MAX_ENTRIES)..current_slot)
+        .rev()
+        .collect()
+}
+
+/// Like `window`, but with every seventh slot skipped, so the entries reach
+/// further back than `SLOT_HASHES_MAX_ENTRIES` slots.
+fn window_with_skipped_slots(current_slot: u64) -> Vec<u64> {
+    (0..current_slot)
+        .rev()
+        .filter(|slot| slot % 7 != 0)
+        .take(SLOT_HASHES_MAX_ENTRIES as usize)
+        .collect()
+}
+
+struct Case {
+    name: &'static str,
+    deactivation_slot: u64,
+    current_slot: u64,
+    slot_hashes: Vec<u64>,
+    closeable: bool,
+}
+
+fn cases() -> Vec<Case> {
+    let current_slot = 1_000;
+    vec![
+        Case {
+            name: "deactivated in the current slot",
+            deactivation_slot: current_slot,
+            current_slot,
+            slot_hashes: window(current_slot),
+            closeable: false,
+        },
+        Case {
+            name: "deactivated at slot 0, still slot 0",
+            deactivation_slot: 0,
+            current_slot: 0,
+            slot_hashes: window(0),
+            closeable: false,
+        },
+        Case {
+            name: "deactivated at slot 0, one slot later",
+            deactivation_slot: 0,
+            current_slot: 1,
+            slot_hashes: window(1),
+            closeable: false,
+        },
+        Case {
+            name: "deactivated at slot 0, exactly MAX_ENTRIES slots ago",
+            deactivation_slot: 0,
+            current_slot: SLOT_HASHES_MAX_ENTRIES,
+            slot_hashes: window(SLOT_HASHES_MAX_ENTRIES),
+            closeable: false,
+        },
+        Case {
+            name: "deactivated at slot 0, MAX_ENTRIES + 1 slots ago",
+            deactivation_slot: 0,
+            current_slot: SLOT_HASHES_MAX_ENTRIES + 1,
+            slot_hashes: window(SLOT_HASHES_MAX_ENTRIES + 1),
+            closeable: true,
+        },
+        Case {
+            name: "deactivated in the previous slot",
+            deactivation_slot: current_slot - 1,
+            current_slot,
+            slot_hashes: window(current_slot),
+            closeable: false,
+        },
+        Case {
+            name: "deactivated exactly MAX_ENTRIES slots ago",
+            deactivation_slot: current_slot - SLOT_HASHES_MAX_ENTRIES,
+            current_slot,
+            slot_hashes: window(current_slot),
+            closeable: false,
+        },
+        Case {
+            name: "deactivated MAX_ENTRIES + 1 slots ago",
+            deactivation_slot: current_slot - SLOT_HASHES_MAX_ENTRIES - 1,
+            current_slot,
+            slot_hashes: window(current_slot),
+            closeable: true,
+        },
+        Case {
+            name: "deactivated MAX_ENTRIES + 1 slots ago, with skipped slots since",
+            deactivation_slot: current_slot - SLOT_HASHES_MAX_ENTRIES - 1,
+            current_slot,
+            slot_hashes: window_with_skipped_slots(current_slot),
+            closeable: false,
+        },
+        Case {
+            name: "deactivation slot skipped, so missing from the slot hashes",
+            deactivation_slot: current_slot - 6,
+            current_slot,
+            slot_hashes: window_with_skipped_slots(current_slot),
+            closeable: true,
+        },
+    ]
+}
+
+fn reference_closeable(case: &Case) -> bool {
+    let slot_hashes = SlotHashes::new(
+        &case
+            .slot_hashes
+            .iter()
+            .map(|slot| (*slot, Hash::new_from_array([1; 32])))
+            .collect::<Vec<_>>(),
+    );
+    let meta = upstream_state::LookupTableMeta {
+        deactivation_slot: case.deactivation_slot,
+        ..upstream_state::LookupTableMeta::default()
+    };
+
+    meta.status(case.current_slot, &slot_hashes) == upstream_state::LookupTableStatus::Deactivated
+}
+
+/// Runs close for `case` and reports whether it succeeded. The only
+/// expected failure is the table still cooling down.
+fn close(case: &Case) -> bool {
+    let lookup_table = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let (slot_key, slot_account) = populated_slot_hashes(&case.slot_hashes);
+
+    let mut mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    mollusk.warp_to_slot(case.current_slot);
+    let context = mollusk.with_context(HashMap::from([
+        (
+            lookup_table,
+            make_lookup_table_account(Some(AUTHORITY), &[], case.deactivation_slot),
+        ),
+        (AUTHORITY, Account::default()),
+        (recipient, Account::default()),
+        (slot_key, slot_account),
+    ]));
+
+    let result =
+        context.process_instruction(&close_instruction(lookup_table, AUTHORITY, recipient));
+    match result.program_result {
+        ProgramResult::Success => true,
+        ProgramResult::Failure(ProgramError::InvalidArgument) => false,
+        other => panic!("{}: unexpected {other:?}", case.name),
+    }
+}
+
+#[test]
+fn test_close_cooldown_matches_reference() {
+    for case in cases() {
+        assert_eq!(reference_closeable(&case), case.closeable, "{}", case.name);
+        assert_eq!(close(&case), case.closeable, "{}", case.name);
+    }
+}
@@ -0,0 +1,567 @@
+//! Shared setup for the mollusk integration tests: program constants,
+//! instruction builders, account fixtures and state assertions.
+//!
+//! Each test binary only uses part of this module.
+#![allow(dead_code)]
+
+use mollusk_svm::{program, result::Check, sysvar, Mollusk, MolluskContext};
+use p_address_lookup_table::{
+    instruction::InstructionData,
+    interface::{
+        ADDRESSES_READY, ALLOWLIST_SEED, AUTHORITY_OFFSET, AUTHORITY_TAG_OFFSET, CLEAR_ALLOWLIST,
+        COLLECT_ADDRESSES, DEACTIVATION_SLOT_OFFSET, EXTEND_ALLOWLIST, EXTEND_LOOKUP_TABLE_CHECKED,
+        EXTEND_LOOKUP_TABLE_WITH_ALLOWLIST, GET_AGE, GET_VERSION, LOOKUP_TABLE_DISCRIMINATOR,
+        REACTIVATE_LOOKUP_TABLE, RENT_STATUS, TOP_UP_RENT, TRANSFER_AUTHORITY,
+    },
+    state::{LookupTable, LookupTableMeta},
+};
+use pinocchio::sysvars::clock::Slot;
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program::example_mocks::solana_sdk::system_program;
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+
+pub const PROGRAM_FILE_NAME: &str = "p_address_lookup_table";
+pub const PROGRAM_ID: Pubkey =
+    Pubkey::new_from_array(p_address_lookup_table::LOOKUP_TABLE_PROGRAM_ID);
+pub const AUTHORITY: Pubkey = Pubkey::from_str_const("Authority1111111111111111111111111111111111");
+pub const AUTHORITY2: Pubkey =
+    Pubkey::from_str_const("Authority2111111111111111111111111111111111");
+pub const PAYER: Pubkey = Pubkey::from_str_const("Payer11111111111111111111111111111111111111");
+
+pub use p_address_lookup_table::interface::{LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE};
+pub const SLOT_HASHES_MAX_ENTRIES: u64 = 512;
+pub const SYSVAR_OWNER: Pubkey =
+    Pubkey::from_str_const("Sysvar1111111111111111111111111111111111111");
+
+pub type Context = MolluskContext<HashMap<Pubkey, Account>>;
+
+// Instruction builders.
+
+pub fn create_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    recent_slot: u64,
+    bump: u8,
+) -> Instruction {
+    let (slot_key, _slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(slot_key, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: InstructionData::Create { recent_slot, bump }.serialize(),
+    }
+}
+
+pub fn extend_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    new_addresses: &[Pubkey],
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: InstructionData::Extend {
+            addresses: new_addresses.iter().map(Pubkey::to_bytes).collect(),
+        }
+        .serialize(),
+    }
+}
+
+pub fn extend_with_allowlist_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    allowlist: Pubkey,
+    payer: Pubkey,
+    new_addresses: &[Pubkey],
+) -> Instruction {
+    let mut instruction = extend_instruction(lookup_table, authority, payer, new_addresses);
+    instruction.data[0..4].copy_from_slice(&EXTEND_LOOKUP_TABLE_WITH_ALLOWLIST.to_le_bytes());
+    instruction
+        .accounts
+        .insert(2, AccountMeta::new_readonly(allowlist, false));
+    instruction
+}
+
+/// The allowlist address of `lookup_table` and its bump.
+pub fn allowlist_address(lookup_table: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ALLOWLIST_SEED, lookup_table.as_ref()], &PROGRAM_ID)
+}
+
+pub fn extend_allowlist_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    new_addresses: &[Pubkey],
+) -> Instruction {
+    let (allowlist, bump) = allowlist_address(lookup_table);
+    let mut instruction = extend_instruction(lookup_table, authority, payer, new_addresses);
+    instruction.data[0..4].copy_from_slice(&EXTEND_ALLOWLIST.to_le_bytes());
+    instruction.data.insert(4, bump);
+    instruction
+        .accounts
+        .insert(2, AccountMeta::new(allowlist, false));
+    instruction
+}
+
+pub fn clear_allowlist_instruction(
+    lookup_table: Pubkey,
+    controller: Pubkey,
+    recipient: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(controller, true),
+            AccountMeta::new(allowlist_address(lookup_table).0, false),
+            AccountMeta::new(recipient, false),
+        ],
+        data: CLEAR_ALLOWLIST.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn extend_checked_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    expected_version: u8,
+    new_addresses: &[Pubkey],
+) -> Instruction {
+    let mut instruction = extend_instruction(lookup_table, authority, payer, new_addresses);
+    instruction.data[0..4].copy_from_slice(&EXTEND_LOOKUP_TABLE_CHECKED.to_le_bytes());
+    instruction.data.insert(4, expected_version);
+    instruction
+}
+
+pub fn freeze_instruction(lookup_table: Pubkey, authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data: InstructionData::Freeze.serialize(),
+    }
+}
+
+pub fn deactivate_instruction(lookup_table: Pubkey, authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data: InstructionData::Deactivate.serialize(),
+    }
+}
+
+pub fn deactivate_with_reason_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    reason: u8,
+) -> Instruction {
+    let mut instruction = deactivate_instruction(lookup_table, authority);
+    instruction.data.push(reason);
+    instruction
+}
+
+pub fn close_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    recipient: Pubkey,
+) -> Instruction {
+    let (slot_key, _slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(recipient, false),
+            AccountMeta::new_readonly(slot_key, false),
+        ],
+        data: InstructionData::Close.serialize(),
+    }
+}
+
+pub fn collect_addresses_instruction(lookup_tables: &[Pubkey]) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: lookup_tables
+            .iter()
+            .map(|lookup_table| AccountMeta::new_readonly(*lookup_table, false))
+            .collect(),
+        data: COLLECT_ADDRESSES.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn transfer_authority_instruction(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    new_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new_readonly(new_authority, false),
+        ],
+        data: TRANSFER_AUTHORITY.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn top_up_rent_instruction(lookup_table: Pubkey, payer: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: TOP_UP_RENT.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn addresses_ready_instruction(lookup_table: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(lookup_table, false)],
+        data: ADDRESSES_READY.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn rent_status_instruction(lookup_table: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(lookup_table, false)],
+        data: RENT_STATUS.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn get_age_instruction(lookup_table: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(lookup_table, false)],
+        data: GET_AGE.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn get_version_instruction() -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![],
+        data: GET_VERSION.to_le_bytes().to_vec(),
+    }
+}
+
+pub fn reactivate_instruction(lookup_table: Pubkey, authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data: REACTIVATE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+    }
+}
+
+// Accounts and sysvars.
+
+/// Builds a slot hashes sysvar account holding `slots` (most recent first),
+/// sized like the real sysvar, or larger to model a cluster that keeps more
+/// than `SLOT_HASHES_MAX_ENTRIES` entries.
+pub fn populated_slot_hashes(slots: &[Slot]) -> (Pubkey, Account) {
+    let (slot_key, _slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+
+    let capacity = slots.len().max(SLOT_HASHES_MAX_ENTRIES as usize);
+    let mut data = vec![0; 8 + capacity * 40];
+    data[0..8].copy_from_slice(&(slots.len() as u64).to_le_bytes());
+    for (i, slot) in slots.iter().enumerate() {
+        let offset = 8 + i * 40;
+        data[offset..offset + 8].copy_from_slice(&slot.to_le_bytes());
+        data[offset + 8..offset + 40].copy_from_slice(&[1; 32]);
+    }
+
+    (
+        slot_key,
+        Account {
+            lamports: 1,
+            data,
+            owner: SYSVAR_OWNER,
+            ..Account::default()
+        },
+    )
+}
+
+/// Builds a rent-exempt table account directly, for states that would take
+/// several instructions to reach. `None` gives a frozen table, and
+/// `u64::MAX` as `deactivation_slot` an active one. The addresses are all
+/// active from slot 1 on.
+pub fn make_lookup_table_account(
+    authority: Option<Pubkey>,
+    addresses: &[Pubkey],
+    deactivation_slot: Slot,
+) -> Account {
+    let mut data = vec![0; LOOKUP_TABLE_META_SIZE];
+    data[0..4].copy_from_slice(&LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes());
+    data[DEACTIVATION_SLOT_OFFSET..DEACTIVATION_SLOT_OFFSET + 8]
+        .copy_from_slice(&deactivation_slot.to_le_bytes());
+    if let Some(authority) = authority {
+        data[AUTHORITY_TAG_OFFSET] = 1;
+        data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + 32].copy_from_slice(authority.as_ref());
+    }
+    for address in addresses {
+        data.extend_from_slice(address.as_ref());
+    }
+
+    Account {
+        lamports: solana_rent::Rent::default().minimum_balance(data.len()),
+        data,
+        owner: PROGRAM_ID,
+        ..Account::default()
+    }
+}
+
+/// Moves the clock, and with it the slot seen by the program, to `slot`.
+pub fn advance_clock(mollusk: &mut Mollusk, slot: Slot) {
+    mollusk.warp_to_slot(slot);
+}
+
+/// Creates a fresh lookup table for `authority` at recent slot 0 in its own
+/// mollusk context, funded by `payer`.
+pub fn create_lookup_table(
+    authority: Pubkey,
+    payer: Pubkey,
+    payer_lamports: u64,
+) -> (Context, Pubkey) {
+    let (slot_key, slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        payer,
+        Account {
+            lamports: payer_lamports,
+            ..Account::default()
+        },
+    );
+    accounts.insert(slot_key, slot_account);
+    accounts.insert(
+        program::keyed_account_for_system_program().0,
+        program::keyed_account_for_system_program().1,
+    );
+
+    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    let context = mollusk.with_context(accounts);
+    let lookup_table = add_lookup_table(&context, authority, payer);
+
+    (context, lookup_table)
+}
+
+/// Creates another lookup table for `authority` at recent slot 0 within an
+/// existing context.
+pub fn add_lookup_table(context: &Context, authority: Pubkey, payer: Pubkey) -> Pubkey {
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+
+    {
+        let mut accounts = context.account_store.borrow_mut();
+        accounts.entry(authority).or_default();
+        accounts.insert(lookup_table, Account::default());
+    }
+
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, payer, recent_slot, bump),
+        &[Check::success()],
+    );
+
+    lookup_table
+}
+
+/// A lookup table created at recent slot 0 in its own mollusk context, with
+/// shortcuts for the common authority operations.
+pub struct TestTable {
+    pub context: Context,
+    pub key: Pubkey,
+    pub authority: Pubkey,
+    pub payer: Pubkey,
+}
+
+impl Default for TestTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestTable {
+    /// Creates a table owned by `AUTHORITY` and paid for by a well funded
+    /// `PAYER`.
+    pub fn new() -> Self {
+        Self::create(AUTHORITY, PAYER, 10_000_000_000)
+    }
+
+    pub fn create(authority: Pubkey, payer: Pubkey, payer_lamports: u64) -> Self {
+        let (context, key) = create_lookup_table(authority, payer, payer_lamports);
+        Self {
+            context,
+            key,
+            authority,
+            payer,
+        }
+    }
+
+    /// Creates a table already extended with `addresses`.
+    pub fn with_addresses(addresses: &[Pubkey]) -> Self {
+        let table = Self::new();
+        table.extend(addresses);
+        table
+    }
+
+    pub fn extend(&self, addresses: &[Pubkey]) {
+        self.context.process_and_validate_instruction(
+            &extend_instruction(self.key, self.authority, self.payer, addresses),
+            &[Check::success()],
+        );
+    }
+
+    pub fn freeze(&self) {
+        self.context.process_and_validate_instruction(
+            &freeze_instruction(self.key, self.authority),
+            &[Check::success()],
+        );
+    }
+
+    pub fn deactivate(&self) {
+        self.context.process_and_validate_instruction(
+            &deactivate_instruction(self.key, self.authority),
+            &[Check::success()],
+        );
+    }
+
+    pub fn advance_clock(&mut self, slot: Slot) {
+        advance_clock(&mut self.context.mollusk, slot);
+    }
+
+    pub fn meta(&self) -> LookupTableMeta {
+        meta_of(&self.context, &self.key)
+    }
+
+    pub fn addresses(&self) -> Vec<Pubkey> {
+        addresses_of(&self.context, &self.key)
+    }
+
+    pub fn lamports(&self) -> u64 {
+        lamports_of(&self.context, &self.key)
+    }
+
+    pub fn assert_table(&self, addresses: &[Pubkey], frozen: bool, deactivated: bool) {
+        assert_table(&self.context, &self.key, addresses, frozen, deactivated);
+    }
+}
+
+// State readers and assertions.
+
+pub fn lamports_of(context: &Context, key: &Pubkey) -> u64 {
+    context.account_store.borrow().get(key).unwrap().lamports
+}
+
+/// Wraps an account's data in the program's zero-copy table view.
+pub fn lookup_table_view(account: &Account) -> LookupTable<'_> {
+    LookupTable::from_bytes(&account.data).unwrap()
+}
+
+pub fn meta_of(context: &Context, key: &Pubkey) -> LookupTableMeta {
+    lookup_table_view(context.account_store.borrow().get(key).unwrap()).meta()
+}
+
+pub fn addresses_len_of(context: &Context, key: &Pubkey) -> usize {
+    let store = context.account_store.borrow();
+    (store.get(key).unwrap().data.len() - LOOKUP_TABLE_META_SIZE) / 32
+}
+
+pub fn addresses_of(context: &Context, key: &Pubkey) -> Vec<Pubkey> {
+    let store = context.account_store.borrow();
+    lookup_table_view(store.get(key).unwrap())
+        .addresses()
+        .iter()
+        .map(|address| Pubkey::new_from_array(*address))
+        .collect()
+}
+
+/// Asserts the stored addresses of a table and whether it is frozen and
+/// deactivated.
+pub fn assert_table(
+    context: &Context,
+    key: &Pubkey,
+    addresses: &[Pubkey],
+    frozen: bool,
+    deactivated: bool,
+) {
+    let meta = meta_of(context, key);
+    assert_eq!(addresses_of(context, key), addresses);
+    assert_eq!(meta.authority_tag == 0, frozen, "frozen");
+    assert_eq!(
+        meta.deactivation_slot != u64::MAX,
+        deactivated,
+        "deactivated"
+    );
+    assert_eq!(meta.is_frozen(), frozen, "is_frozen");
+    assert_eq!(meta.is_deactivated(), deactivated, "is_deactivated");
+    assert_eq!(meta.is_active(), !deactivated, "is_active");
+}
+
+thread_local! {
+    static CAPTURED_LOGS: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Collects the runtime's `log` output, which includes every `Program log:`
+/// line, for the current thread only so parallel tests don't interleave.
+struct LogCapture;
+
+impl log::Log for LogCapture {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        CAPTURED_LOGS.with(|logs| logs.borrow_mut().push(record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+static LOG_CAPTURE: LogCapture = LogCapture;
+
+/// Runs `instruction`, validating the result against `checks`, and returns
+/// the logs it produced.
+pub fn logs_of(context: &Context, instruction: &Instruction, checks: &[Check]) -> Vec<String> {
+    let _ = log::set_logger(&LOG_CAPTURE);
+    log::set_max_level(log::LevelFilter::Debug);
+    CAPTURED_LOGS.with(|logs| logs.borrow_mut().clear());
+
+    context.process_and_validate_instruction(instruction, checks);
+
+    CAPTURED_LOGS.with(|logs| logs.take())
+}
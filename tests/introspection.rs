@@ -0,0 +1,190 @@
+//! `lookup_table_instructions` over instructions sysvar payloads built the
+//! way the runtime serializes them, mixing this program's instructions with
+//! unrelated ones.
+
+mod common;
+
+use common::{
+    close_instruction, create_instruction, extend_instruction, freeze_instruction, PROGRAM_ID,
+};
+use p_address_lookup_table::{
+    instruction::ProgramInstruction,
+    interface::{LookupTableError, CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT},
+    introspection::lookup_table_instructions,
+};
+use pinocchio::program_error::ProgramError;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+/// Serializes `instructions` like the instructions sysvar: the count, an
+/// offset per instruction, the instructions and the current index.
+fn instructions_sysvar(instructions: &[Instruction], current_index: u16) -> Vec<u8> {
+    let mut data = (instructions.len() as u16).to_le_bytes().to_vec();
+    let offsets_start = data.len();
+    data.resize(offsets_start + instructions.len() * 2, 0);
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        let offset = (data.len() as u16).to_le_bytes();
+        data[offsets_start + index * 2..][..2].copy_from_slice(&offset);
+
+        data.extend_from_slice(&(instruction.accounts.len() as u16).to_le_bytes());
+        for meta in &instruction.accounts {
+            data.push(meta.is_signer as u8 | (meta.is_writable as u8) << 1);
+            data.extend_from_slice(meta.pubkey.as_ref());
+        }
+        data.extend_from_slice(instruction.program_id.as_ref());
+        data.extend_from_slice(&(instruction.data.len() as u16).to_le_bytes());
+        data.extend_from_slice(&instruction.data);
+    }
+
+    data.extend_from_slice(&current_index.to_le_bytes());
+    data
+}
+
+fn unrelated_instruction(data: &[u8]) -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![AccountMeta::new(Pubkey::new_unique(), true)],
+        data: data.to_vec(),
+    }
+}
+
+#[test]
+fn test_lookup_table_instructions_skip_other_programs() {
+    let table = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let new_addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let mut close = close_instruction(table, authority, payer);
+    close.data.push(CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT);
+
+    // Unrelated instructions that would decode as ours if the program id
+    // were not checked.
+    let instructions = [
+        unrelated_instruction(&[]),
+        create_instruction(table, authority, payer, 7, 254),
+        unrelated_instruction(&2u32.to_le_bytes()),
+        extend_instruction(table, authority, payer, &new_addresses),
+        unrelated_instruction(b"memo"),
+        close,
+    ];
+    let data = instructions_sysvar(&instructions, 4);
+
+    let program_id = PROGRAM_ID.to_bytes();
+    let found: Vec<_> = lookup_table_instructions(&data, &program_id)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    let indexes: Vec<usize> = found.iter().map(|(index, _, _)| *index).collect();
+    assert_eq!(indexes, [1, 3, 5]);
+
+    assert_eq!(
+        found[0].1,
+        ProgramInstruction::CreateLookupTable {
+            recent_slot: 7,
+            bump_seed: 254,
+        }
+    );
+
+    let packed_addresses: Vec<u8> = new_addresses.iter().flat_map(|a| a.to_bytes()).collect();
+    assert_eq!(
+        found[1].1,
+        ProgramInstruction::ExtendLookupTable {
+            addresses: &packed_addresses,
+        }
+    );
+    let extend_accounts = found[1].2;
+    assert_eq!(extend_accounts.len(), instructions[3].accounts.len());
+    assert_eq!(extend_accounts.key(0), Some(&table.to_bytes()));
+    assert_eq!(extend_accounts.is_writable(0), Some(true));
+    assert_eq!(extend_accounts.is_signer(0), Some(false));
+    assert_eq!(extend_accounts.is_signer(1), Some(true));
+    assert_eq!(extend_accounts.key(extend_accounts.len()), None);
+    let extend_keys: Vec<[u8; 32]> = extend_accounts.keys().copied().collect();
+    let expected_keys: Vec<[u8; 32]> = instructions[3]
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey.to_bytes())
+        .collect();
+    assert_eq!(extend_keys, expected_keys);
+
+    assert_eq!(
+        found[2].1,
+        ProgramInstruction::CloseLookupTable {
+            flags: CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT,
+        }
+    );
+}
+
+#[test]
+fn test_lookup_table_instructions_find_extend_of_table() {
+    let table = Pubkey::new_unique();
+    let other_table = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    // What a registry program would check: an extend of `table` in the
+    // same transaction, not just any lookup table instruction.
+    let extends_table = |instructions: &[Instruction]| {
+        let data = instructions_sysvar(instructions, 0);
+        let program_id = PROGRAM_ID.to_bytes();
+        lookup_table_instructions(&data, &program_id)
+            .unwrap()
+            .map(Result::unwrap)
+            .any(|(_, instruction, accounts)| {
+                matches!(instruction, ProgramInstruction::ExtendLookupTable { .. })
+                    && accounts.key(0) == Some(&table.to_bytes())
+            })
+    };
+
+    assert!(extends_table(&[
+        unrelated_instruction(&[]),
+        extend_instruction(table, authority, payer, &[Pubkey::new_unique()]),
+    ]));
+    assert!(!extends_table(&[
+        freeze_instruction(table, authority),
+        extend_instruction(other_table, authority, payer, &[Pubkey::new_unique()]),
+    ]));
+    assert!(!extends_table(&[unrelated_instruction(&[])]));
+}
+
+#[test]
+fn test_lookup_table_instructions_report_undecodable_data() {
+    let table = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+
+    let mut unknown = freeze_instruction(table, authority);
+    unknown.data = 99u32.to_le_bytes().to_vec();
+    let data = instructions_sysvar(&[unknown, freeze_instruction(table, authority)], 0);
+
+    let program_id = PROGRAM_ID.to_bytes();
+    let mut found = lookup_table_instructions(&data, &program_id).unwrap();
+    assert_eq!(
+        found.next().unwrap().unwrap_err(),
+        ProgramError::Custom(LookupTableError::UnknownInstruction as u32)
+    );
+    // The entries after it are still read.
+    assert_eq!(
+        found.next().unwrap().unwrap().1,
+        ProgramInstruction::FreezeLookupTable
+    );
+    assert!(found.next().is_none());
+}
+
+#[test]
+fn test_lookup_table_instructions_truncated_sysvar() {
+    let table = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let data = instructions_sysvar(&[freeze_instruction(table, authority)], 0);
+    let program_id = PROGRAM_ID.to_bytes();
+
+    assert!(lookup_table_instructions(&[], &program_id).is_err());
+
+    // Cut off the current index and the last byte of the instruction data.
+    let truncated = &data[..data.len() - 3];
+    let mut found = lookup_table_instructions(truncated, &program_id).unwrap();
+    assert!(found.next().unwrap().is_err());
+    assert!(found.next().is_none());
+}
@@ -0,0 +1,227 @@
+//! `resolve_lookup` must load the same addresses as the runtime's lookup in
+//! `solana-address-lookup-table-interface`, which is checked here over a
+//! corpus of table states and index sets.
+
+use p_address_lookup_table::{
+    interface::LOOKUP_TABLE_META_SIZE,
+    message::{
+        can_reference, resolve_lookup, usable_addresses, LoadedAddresses, LookupError,
+        MessageAddressTableLookup,
+    },
+    LookupTable,
+};
+use solana_address_lookup_table_interface::{error::AddressLookupError, state as upstream_state};
+use solana_program::{hash::Hash, slot_hashes::SlotHashes};
+use solana_pubkey::Pubkey;
+use std::borrow::Cow;
+
+const CURRENT_SLOT: u64 = 1_000;
+
+/// Recent slots with every seventh one skipped, most recent first.
+fn recent_slots() -> Vec<u64> {
+    (CURRENT_SLOT - 512..CURRENT_SLOT)
+        .rev()
+        .filter(|slot| slot % 7 != 0)
+        .collect()
+}
+
+fn slot_hashes_data(slots: &[u64]) -> Vec<u8> {
+    let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+    for slot in slots {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(&[1; 32]);
+    }
+    data
+}
+
+fn upstream_resolve(
+    data: &[u8],
+    lookup: &MessageAddressTableLookup,
+    slot_hashes: &SlotHashes,
+) -> Result<LoadedAddresses, LookupError> {
+    let table = upstream_state::AddressLookupTable::deserialize(data)
+        .map_err(|_| LookupError::InvalidAccountData)?;
+    let resolve = |indexes: &[u8]| {
+        table
+            .lookup(CURRENT_SLOT, indexes, slot_hashes)
+            .map(|addresses| addresses.iter().map(|a| a.to_bytes()).collect())
+            .map_err(|error| match error {
+                AddressLookupError::LookupTableAccountNotFound => {
+                    LookupError::LookupTableAccountNotFound
+                }
+                AddressLookupError::InvalidLookupIndex => LookupError::InvalidLookupIndex,
+                _ => LookupError::InvalidAccountData,
+            })
+    };
+
+    Ok(LoadedAddresses {
+        writable: resolve(&lookup.writable_indexes)?,
+        readonly: resolve(&lookup.readonly_indexes)?,
+    })
+}
+
+#[test]
+fn test_resolve_lookup_matches_runtime() {
+    let slots = recent_slots();
+    let slot_hashes_bytes = slot_hashes_data(&slots);
+    let slot_hashes = SlotHashes::new(
+        &slots
+            .iter()
+            .map(|slot| (*slot, Hash::new_from_array([1; 32])))
+            .collect::<Vec<_>>(),
+    );
+
+    let deactivation_slots = [
+        u64::MAX,           // active
+        CURRENT_SLOT,       // deactivated in this slot
+        CURRENT_SLOT - 10,  // still in the slot hashes
+        CURRENT_SLOT - 6,   // a skipped slot, so already deactivated
+        CURRENT_SLOT - 600, // past the slot hashes
+    ];
+    // (last extended slot, start index): extended in this slot or before.
+    let extensions = [(CURRENT_SLOT, 2), (CURRENT_SLOT - 1, 2)];
+    let index_sets: [&[u8]; 5] = [&[], &[0], &[4, 1], &[2, 5], &[255]];
+
+    let mut cases = 0;
+    for addresses_len in [0, 5] {
+        let addresses: Vec<Pubkey> = (0..addresses_len).map(|_| Pubkey::new_unique()).collect();
+        for deactivation_slot in deactivation_slots {
+            for (last_extended_slot, start_index) in extensions {
+                let table = upstream_state::AddressLookupTable {
+                    meta: upstream_state::LookupTableMeta {
+                        deactivation_slot,
+                        last_extended_slot,
+                        last_extended_slot_start_index: start_index.min(addresses_len as u8),
+                        authority: Some(Pubkey::new_unique().to_bytes().into()),
+                        ..upstream_state::LookupTableMeta::default()
+                    },
+                    addresses: Cow::Owned(addresses.iter().map(|a| a.to_bytes().into()).collect()),
+                };
+                let data = table.serialize_for_tests().unwrap();
+
+                for writable_indexes in index_sets {
+                    for readonly_indexes in index_sets {
+                        let lookup = MessageAddressTableLookup {
+                            account_key: [9; 32],
+                            writable_indexes: writable_indexes.to_vec(),
+                            readonly_indexes: readonly_indexes.to_vec(),
+                        };
+                        assert_eq!(
+                            resolve_lookup(&data, &lookup, CURRENT_SLOT, &slot_hashes_bytes),
+                            upstream_resolve(&data, &lookup, &slot_hashes),
+                            "deactivation slot {deactivation_slot}, extended at \
+                             {last_extended_slot}, {addresses_len} addresses, {lookup:?}",
+                        );
+                        cases += 1;
+                    }
+                }
+            }
+        }
+    }
+    assert_eq!(cases, 2 * 5 * 2 * 5 * 5);
+}
+
+#[test]
+fn test_resolve_lookup_rejects_uninitialized_data() {
+    let lookup = MessageAddressTableLookup::default();
+    assert_eq!(
+        resolve_lookup(
+            &[0; LOOKUP_TABLE_META_SIZE],
+            &lookup,
+            CURRENT_SLOT,
+            &slot_hashes_data(&[])
+        ),
+        Err(LookupError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn test_resolve_lookup_with_start_index_past_addresses() {
+    let addresses: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+    let table = upstream_state::AddressLookupTable {
+        meta: upstream_state::LookupTableMeta {
+            last_extended_slot: CURRENT_SLOT,
+            last_extended_slot_start_index: 7,
+            ..upstream_state::LookupTableMeta::default()
+        },
+        addresses: Cow::Owned(addresses.iter().map(|a| a.to_bytes().into()).collect()),
+    };
+    let data = table.serialize_for_tests().unwrap();
+    let slot_hashes = slot_hashes_data(&recent_slots());
+
+    let lookup = |indexes: Vec<u8>| MessageAddressTableLookup {
+        account_key: Pubkey::new_unique().to_bytes(),
+        writable_indexes: indexes,
+        readonly_indexes: vec![],
+    };
+    assert_eq!(
+        resolve_lookup(&data, &lookup(vec![1]), CURRENT_SLOT, &slot_hashes),
+        Ok(LoadedAddresses {
+            writable: vec![addresses[1].to_bytes()],
+            readonly: vec![],
+        })
+    );
+    assert_eq!(
+        resolve_lookup(&data, &lookup(vec![2]), CURRENT_SLOT, &slot_hashes),
+        Err(LookupError::InvalidLookupIndex)
+    );
+    assert_eq!(
+        usable_addresses(&data, CURRENT_SLOT),
+        Ok((addresses.iter().map(Pubkey::to_bytes).collect(), vec![]))
+    );
+}
+
+#[test]
+fn test_usable_addresses_split_at_slot_boundary() {
+    let addresses: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let table = upstream_state::AddressLookupTable {
+        meta: upstream_state::LookupTableMeta {
+            last_extended_slot: CURRENT_SLOT,
+            last_extended_slot_start_index: 3,
+            authority: Some(Pubkey::new_unique().to_bytes().into()),
+            ..upstream_state::LookupTableMeta::default()
+        },
+        addresses: Cow::Owned(addresses.iter().map(|a| a.to_bytes().into()).collect()),
+    };
+    let data = table.serialize_for_tests().unwrap();
+    let meta = LookupTable::from_bytes(&data).unwrap().meta();
+    let addresses: Vec<[u8; 32]> = addresses.iter().map(Pubkey::to_bytes).collect();
+    let start_index = meta.last_extended_slot_start_index as usize;
+
+    // Up to the slot of the last extend, its batch is still pending.
+    for slot in [meta.last_extended_slot - 1, meta.last_extended_slot] {
+        assert_eq!(
+            usable_addresses(&data, slot),
+            Ok((
+                addresses[..start_index].to_vec(),
+                addresses[start_index..].to_vec()
+            ))
+        );
+        assert!(can_reference(&data, &addresses[start_index - 1], slot));
+        assert!(!can_reference(&data, &addresses[start_index], slot));
+    }
+
+    let next_slot = meta.last_extended_slot + 1;
+    assert_eq!(
+        usable_addresses(&data, next_slot),
+        Ok((addresses.clone(), vec![]))
+    );
+    assert!(addresses
+        .iter()
+        .all(|address| can_reference(&data, address, next_slot)));
+    assert!(!can_reference(
+        &data,
+        &Pubkey::new_unique().to_bytes(),
+        next_slot
+    ));
+}
+
+#[test]
+fn test_usable_addresses_rejects_uninitialized_data() {
+    let data = [0; LOOKUP_TABLE_META_SIZE];
+    assert_eq!(
+        usable_addresses(&data, CURRENT_SLOT),
+        Err(LookupError::InvalidAccountData)
+    );
+    assert!(!can_reference(&data, &[0; 32], CURRENT_SLOT));
+}
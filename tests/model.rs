@@ -0,0 +1,403 @@
+//! Model-based test: random instruction sequences are applied both to a plain
+//! Rust model of a lookup table and to the program under mollusk, and the
+//! observable state must match after every step.
+//!
+//! Set `MODEL_TEST_ITERATIONS` to run more sequences than the default.
+
+mod common;
+
+use common::{
+    LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE, PROGRAM_FILE_NAME, PROGRAM_ID,
+    SLOT_HASHES_MAX_ENTRIES, SYSVAR_OWNER,
+};
+use mollusk_svm::{program, result::Check, sysvar, Mollusk, MolluskContext};
+use p_address_lookup_table::interface::{
+    AUTHORITY_OFFSET, AUTHORITY_TAG_OFFSET, CLOSE_LOOKUP_TABLE, CREATE_LOOKUP_TABLE,
+    DEACTIVATE_LOOKUP_TABLE, DEACTIVATION_SLOT_OFFSET, EXTEND_LOOKUP_TABLE, FREEZE_LOOKUP_TABLE,
+    LAST_EXTENDED_SLOT_OFFSET, LAST_EXTENDED_SLOT_START_INDEX_OFFSET, LOOKUP_TABLE_DISCRIMINATOR,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+
+const DEFAULT_ITERATIONS: usize = 200;
+const STEPS_PER_ITERATION: usize = 16;
+
+/// xorshift64* keeps the sequences reproducible without extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+#[derive(Debug)]
+struct ModelTable {
+    authority: Option<Pubkey>,
+    deactivation_slot: u64,
+    last_extended_slot: u64,
+    last_extended_slot_start_index: u8,
+    addresses: Vec<Pubkey>,
+    closed: bool,
+}
+
+impl ModelTable {
+    fn new(authority: Pubkey) -> Self {
+        Self {
+            authority: Some(authority),
+            deactivation_slot: u64::MAX,
+            last_extended_slot: 0,
+            last_extended_slot_start_index: 0,
+            addresses: Vec::new(),
+            closed: false,
+        }
+    }
+
+    fn check_authority(&self, signer: &Pubkey) -> Result<(), ProgramError> {
+        match &self.authority {
+            None => Err(ProgramError::Immutable),
+            Some(authority) if authority != signer => Err(ProgramError::IncorrectAuthority),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn extend(
+        &mut self,
+        signer: &Pubkey,
+        new_addresses: &[Pubkey],
+        slot: u64,
+    ) -> Result<(), ProgramError> {
+        self.check_authority(signer)?;
+        if self.deactivation_slot != u64::MAX {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if self.addresses.len() >= LOOKUP_TABLE_MAX_ADDRESSES {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if new_addresses.is_empty()
+            || self.addresses.len() + new_addresses.len() > LOOKUP_TABLE_MAX_ADDRESSES
+        {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if slot != self.last_extended_slot {
+            self.last_extended_slot = slot;
+            self.last_extended_slot_start_index = self.addresses.len() as u8;
+        }
+        self.addresses.extend_from_slice(new_addresses);
+        Ok(())
+    }
+
+    fn freeze(&mut self, signer: &Pubkey) -> Result<(), ProgramError> {
+        self.check_authority(signer)?;
+        if self.deactivation_slot != u64::MAX {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if self.addresses.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        self.authority = None;
+        Ok(())
+    }
+
+    fn deactivate(&mut self, signer: &Pubkey, slot: u64) -> Result<(), ProgramError> {
+        self.check_authority(signer)?;
+        if self.deactivation_slot != u64::MAX {
+            return Err(ProgramError::InvalidArgument);
+        }
+        self.deactivation_slot = slot;
+        Ok(())
+    }
+
+    fn close(&mut self, signer: &Pubkey, slot: u64) -> Result<(), ProgramError> {
+        self.check_authority(signer)?;
+        // Slot hashes hold the previous `SLOT_HASHES_MAX_ENTRIES` slots.
+        if self.deactivation_slot == u64::MAX
+            || slot - self.deactivation_slot <= SLOT_HASHES_MAX_ENTRIES
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+        self.closed = true;
+        Ok(())
+    }
+
+    fn assert_matches(&self, account: &Account) {
+        if self.closed {
+            assert_eq!(account.lamports, 0);
+            assert!(account.data.is_empty());
+            return;
+        }
+
+        let data = &account.data;
+        let authority = &data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + 32];
+        assert_eq!(data[0..4], LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes());
+        assert_eq!(
+            data[DEACTIVATION_SLOT_OFFSET..DEACTIVATION_SLOT_OFFSET + 8],
+            self.deactivation_slot.to_le_bytes()
+        );
+        assert_eq!(
+            data[LAST_EXTENDED_SLOT_OFFSET..LAST_EXTENDED_SLOT_OFFSET + 8],
+            self.last_extended_slot.to_le_bytes()
+        );
+        assert_eq!(
+            data[LAST_EXTENDED_SLOT_START_INDEX_OFFSET],
+            self.last_extended_slot_start_index
+        );
+        match &self.authority {
+            Some(expected) => {
+                assert_eq!(data[AUTHORITY_TAG_OFFSET], 1);
+                assert_eq!(authority, expected.as_ref());
+            }
+            None => {
+                assert_eq!(data[AUTHORITY_TAG_OFFSET], 0);
+                assert_eq!(authority, [0; 32]);
+            }
+        }
+
+        let addresses: Vec<Pubkey> = data[LOOKUP_TABLE_META_SIZE..]
+            .chunks_exact(32)
+            .map(|chunk| Pubkey::new_from_array(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(addresses, self.addresses);
+    }
+}
+
+/// Slot hashes as the cluster would hold them at `current_slot`.
+fn slot_hashes_account(current_slot: u64) -> Account {
+    let slots: Vec<u64> = (current_slot.saturating_sub(SLOT_HASHES_MAX_ENTRIES)..current_slot)
+        .rev()
+        .collect();
+
+    let mut data = vec![0; 8 + SLOT_HASHES_MAX_ENTRIES as usize * 40];
+    data[0..8].copy_from_slice(&(slots.len() as u64).to_le_bytes());
+    for (i, slot) in slots.iter().enumerate() {
+        let offset = 8 + i * 40;
+        data[offset..offset + 8].copy_from_slice(&slot.to_le_bytes());
+        data[offset + 8..offset + 40].copy_from_slice(&[1; 32]);
+    }
+
+    Account {
+        lamports: 1,
+        data,
+        owner: SYSVAR_OWNER,
+        ..Account::default()
+    }
+}
+
+fn instruction(accounts: Vec<AccountMeta>, data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
+
+struct Harness {
+    context: MolluskContext<HashMap<Pubkey, Account>>,
+    slot: u64,
+    slot_hashes: Pubkey,
+    lookup_table: Pubkey,
+    payer: Pubkey,
+    recipient: Pubkey,
+}
+
+impl Harness {
+    fn warp_to_slot(&mut self, slot: u64) {
+        self.slot = slot;
+        self.context.mollusk.warp_to_slot(slot);
+        self.context
+            .account_store
+            .borrow_mut()
+            .insert(self.slot_hashes, slot_hashes_account(slot));
+    }
+
+    fn process(&self, instruction: &Instruction, expected: Result<(), ProgramError>) {
+        let check = match expected {
+            Ok(()) => Check::success(),
+            Err(error) => Check::err(error),
+        };
+        self.context
+            .process_and_validate_instruction(instruction, &[check]);
+    }
+
+    fn lookup_table_account(&self) -> Account {
+        self.context
+            .account_store
+            .borrow()
+            .get(&self.lookup_table)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+fn run_sequence(seed: u64) {
+    let mut rng = Rng(seed);
+
+    let authority = Pubkey::new_unique();
+    let impostor = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    let (slot_hashes, _) = sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+    let (system_program, system_account) = program::keyed_account_for_system_program();
+
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(impostor, Account::default());
+    accounts.insert(recipient, Account::default());
+    accounts.insert(
+        payer,
+        Account {
+            lamports: 1_000_000_000_000,
+            ..Account::default()
+        },
+    );
+    accounts.insert(lookup_table, Account::default());
+    accounts.insert(system_program, system_account);
+
+    let mut harness = Harness {
+        context: Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME).with_context(accounts),
+        slot: 0,
+        slot_hashes,
+        lookup_table,
+        payer,
+        recipient,
+    };
+    harness.warp_to_slot(1);
+
+    let mut create_data = CREATE_LOOKUP_TABLE.to_le_bytes().to_vec();
+    create_data.extend_from_slice(&recent_slot.to_le_bytes());
+    create_data.push(bump);
+    harness.process(
+        &instruction(
+            vec![
+                AccountMeta::new(lookup_table, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(harness.payer, true),
+                AccountMeta::new_readonly(slot_hashes, false),
+                AccountMeta::new_readonly(system_program, false),
+            ],
+            create_data,
+        ),
+        Ok(()),
+    );
+
+    let mut model = ModelTable::new(authority);
+    model.assert_matches(&harness.lookup_table_account());
+
+    for _ in 0..STEPS_PER_ITERATION {
+        let signer = if rng.chance(15) { impostor } else { authority };
+
+        match rng.below(10) {
+            0..=3 => {
+                let len = match rng.below(10) {
+                    0 => 0,
+                    1 => rng.below(LOOKUP_TABLE_MAX_ADDRESSES as u64 + 1),
+                    _ => 1 + rng.below(30),
+                };
+                let new_addresses: Vec<Pubkey> = (0..len).map(|_| Pubkey::new_unique()).collect();
+
+                let mut data = EXTEND_LOOKUP_TABLE.to_le_bytes().to_vec();
+                data.extend_from_slice(&len.to_le_bytes());
+                for address in &new_addresses {
+                    data.extend_from_slice(address.as_ref());
+                }
+
+                let expected = model.extend(&signer, &new_addresses, harness.slot);
+                harness.process(
+                    &instruction(
+                        vec![
+                            AccountMeta::new(lookup_table, false),
+                            AccountMeta::new_readonly(signer, true),
+                            AccountMeta::new(harness.payer, true),
+                            AccountMeta::new_readonly(system_program, false),
+                        ],
+                        data,
+                    ),
+                    expected,
+                );
+            }
+            4 => {
+                let expected = model.freeze(&signer);
+                harness.process(
+                    &instruction(
+                        vec![
+                            AccountMeta::new(lookup_table, false),
+                            AccountMeta::new_readonly(signer, true),
+                        ],
+                        FREEZE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+                    ),
+                    expected,
+                );
+            }
+            5 => {
+                let expected = model.deactivate(&signer, harness.slot);
+                harness.process(
+                    &instruction(
+                        vec![
+                            AccountMeta::new(lookup_table, false),
+                            AccountMeta::new_readonly(signer, true),
+                        ],
+                        DEACTIVATE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+                    ),
+                    expected,
+                );
+            }
+            6..=7 => {
+                let slot = harness.slot + 1 + rng.below(2 * SLOT_HASHES_MAX_ENTRIES);
+                harness.warp_to_slot(slot);
+            }
+            _ => {
+                let expected = model.close(&signer, harness.slot);
+                harness.process(
+                    &instruction(
+                        vec![
+                            AccountMeta::new(lookup_table, false),
+                            AccountMeta::new_readonly(signer, true),
+                            AccountMeta::new(harness.recipient, false),
+                            AccountMeta::new_readonly(slot_hashes, false),
+                        ],
+                        CLOSE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+                    ),
+                    expected,
+                );
+            }
+        }
+
+        model.assert_matches(&harness.lookup_table_account());
+        if model.closed {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_random_instruction_sequences_match_model() {
+    let iterations = std::env::var("MODEL_TEST_ITERATIONS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    for iteration in 0..iterations {
+        // Seeds must be non-zero for xorshift.
+        run_sequence(iteration as u64 + 1);
+    }
+}
@@ -0,0 +1,247 @@
+//! Hands tables back and forth between the native program and this one:
+//! tables created and extended by the native build are frozen, deactivated
+//! and closed by ours, and the other way round. Every step is also run by
+//! the other program on the same accounts, and both must agree on success
+//! and on the resulting accounts, as they would have to on a cluster that
+//! switches programs mid-life of a table.
+//!
+//! Both programs are deployed at the canonical id, each in its own
+//! `Mollusk`. The native build is the core BPF release of
+//! `solana-program/address-lookup-table`, read from
+//! `tests/fixtures/solana_address_lookup_table_program.so` or from the path
+//! in `NATIVE_LOOKUP_TABLE_ELF`.
+#![cfg(all(feature = "native-interop", not(feature = "custom-program-id")))]
+
+mod common;
+
+use common::{PROGRAM_FILE_NAME, PROGRAM_ID, SLOT_HASHES_MAX_ENTRIES};
+use mollusk_svm::{
+    program::{self, loader_keys::LOADER_V3},
+    result::InstructionResult,
+    Mollusk,
+};
+use p_address_lookup_table::interface::{DEACTIVATION_REASON_OFFSET, VERSION_OFFSET};
+use solana_account::Account;
+use solana_address_lookup_table_interface::instruction as upstream;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const NATIVE_ELF: &str = "tests/fixtures/solana_address_lookup_table_program.so";
+
+/// Re-keys an upstream instruction with this crate's pubkey type.
+fn from_upstream(instruction: Instruction) -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_from_array(instruction.program_id.to_bytes()),
+        accounts: instruction
+            .accounts
+            .iter()
+            .map(|meta| AccountMeta {
+                pubkey: Pubkey::new_from_array(meta.pubkey.to_bytes()),
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: instruction.data,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Program {
+    Native,
+    Ours,
+}
+
+/// The two programs and the accounts they share.
+struct Cluster {
+    native: Mollusk,
+    ours: Mollusk,
+    accounts: HashMap<Pubkey, Account>,
+}
+
+impl Cluster {
+    fn new() -> Self {
+        let path = std::env::var_os("NATIVE_LOOKUP_TABLE_ELF")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(NATIVE_ELF));
+        let elf = std::fs::read(&path).unwrap_or_else(|error| {
+            panic!(
+                "native program binary {} is needed for the interop tests: {error}",
+                path.display()
+            )
+        });
+
+        let mut native = Mollusk::default();
+        native.add_program_with_elf_and_loader(&PROGRAM_ID, &elf, &LOADER_V3);
+        let ours = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+
+        let (system_program, system_account) = program::keyed_account_for_system_program();
+        let mut accounts = HashMap::new();
+        accounts.insert(system_program, system_account);
+
+        Self {
+            native,
+            ours,
+            accounts,
+        }
+    }
+
+    fn warp_to_slot(&mut self, slot: u64) {
+        self.native.warp_to_slot(slot);
+        self.ours.warp_to_slot(slot);
+    }
+
+    fn fund(&mut self, key: Pubkey, lamports: u64) {
+        self.accounts.insert(
+            key,
+            Account {
+                lamports,
+                ..Account::default()
+            },
+        );
+    }
+
+    fn run(&self, program: Program, instruction: &Instruction) -> InstructionResult {
+        let mollusk = match program {
+            Program::Native => &self.native,
+            Program::Ours => &self.ours,
+        };
+        let accounts: Vec<(Pubkey, Account)> = instruction
+            .accounts
+            .iter()
+            .map(|meta| {
+                let account = self.accounts.get(&meta.pubkey).cloned();
+                (meta.pubkey, account.unwrap_or_default())
+            })
+            .collect();
+        mollusk.process_instruction(instruction, &accounts)
+    }
+
+    /// Runs `instruction` with both programs on the same accounts, checks
+    /// that they agree, and keeps the accounts `program` produced.
+    fn step(&mut self, program: Program, instruction: Instruction) {
+        let native = self.run(Program::Native, &instruction);
+        let ours = self.run(Program::Ours, &instruction);
+
+        assert_eq!(
+            native.program_result.is_ok(),
+            ours.program_result.is_ok(),
+            "programs disagree on acceptance: native {:?}, ours {:?}",
+            native.program_result,
+            ours.program_result,
+        );
+        assert!(
+            native.program_result.is_ok(),
+            "both programs rejected the instruction: {:?}",
+            native.program_result,
+        );
+
+        for ((key, native_account), (_, our_account)) in native
+            .resulting_accounts
+            .iter()
+            .zip(&ours.resulting_accounts)
+        {
+            assert_eq!(native_account.lamports, our_account.lamports, "{key}");
+            assert_eq!(native_account.owner, our_account.owner, "{key}");
+            assert_eq!(
+                comparable_data(&native_account.data),
+                comparable_data(&our_account.data),
+                "{key}",
+            );
+        }
+
+        let kept = match program {
+            Program::Native => native,
+            Program::Ours => ours,
+        };
+        self.accounts.extend(kept.resulting_accounts);
+    }
+}
+
+/// Account data without the two meta bytes the native program leaves as
+/// padding, where this program keeps the table version and the deactivation
+/// reason.
+fn comparable_data(data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    if let Some(unused) = data.get_mut(VERSION_OFFSET..=DEACTIVATION_REASON_OFFSET) {
+        unused.fill(0);
+    }
+    data
+}
+
+/// Creates a table with `program` and extends it with three addresses.
+fn create_and_extend(cluster: &mut Cluster, program: Program, authority: Pubkey) -> Pubkey {
+    let payer = Pubkey::new_unique();
+    cluster.fund(payer, 10_000_000_000);
+    cluster.accounts.entry(authority).or_default();
+
+    let (create, lookup_table) =
+        upstream::create_lookup_table(authority.to_bytes().into(), payer.to_bytes().into(), 0);
+    let lookup_table = Pubkey::new_from_array(lookup_table.to_bytes());
+    cluster.step(program, from_upstream(create));
+
+    let extend = upstream::extend_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+        Some(payer.to_bytes().into()),
+        (0..3)
+            .map(|_| Pubkey::new_unique().to_bytes().into())
+            .collect(),
+    );
+    cluster.step(program, from_upstream(extend));
+
+    lookup_table
+}
+
+/// Creates two tables with `creator`, then freezes one and deactivates and
+/// closes the other with `manager`.
+fn hand_over(creator: Program, manager: Program) {
+    let mut cluster = Cluster::new();
+    let authority = Pubkey::new_unique();
+    let closed_authority = Pubkey::new_unique();
+
+    let frozen = create_and_extend(&mut cluster, creator, authority);
+    let closed = create_and_extend(&mut cluster, creator, closed_authority);
+
+    cluster.step(
+        manager,
+        from_upstream(upstream::freeze_lookup_table(
+            frozen.to_bytes().into(),
+            authority.to_bytes().into(),
+        )),
+    );
+
+    cluster.step(
+        manager,
+        from_upstream(upstream::deactivate_lookup_table(
+            closed.to_bytes().into(),
+            closed_authority.to_bytes().into(),
+        )),
+    );
+
+    let recipient = Pubkey::new_unique();
+    cluster.accounts.insert(recipient, Account::default());
+    cluster.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+    cluster.step(
+        manager,
+        from_upstream(upstream::close_lookup_table(
+            closed.to_bytes().into(),
+            closed_authority.to_bytes().into(),
+            recipient.to_bytes().into(),
+        )),
+    );
+
+    assert!(cluster.accounts[&closed].data.is_empty());
+    assert_eq!(cluster.accounts[&closed].lamports, 0);
+}
+
+#[test]
+fn test_native_tables_managed_by_ours() {
+    hand_over(Program::Native, Program::Ours);
+}
+
+#[test]
+fn test_our_tables_managed_by_native() {
+    hand_over(Program::Ours, Program::Native);
+}
@@ -0,0 +1,27 @@
+#![no_std]
+
+use p_address_lookup_table::{check_id, LookupTable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+/// Checks that `table_info` is a lookup table holding `expected` at `index`.
+pub fn verify_address(
+    table_info: &AccountInfo,
+    current_slot: u64,
+    index: u8,
+    expected: &Pubkey,
+) -> Result<(), ProgramError> {
+    if !check_id(table_info.owner()) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let data = table_info.try_borrow_data()?;
+    let table = LookupTable::from_bytes(&data)?;
+
+    let mut resolved = [[0u8; 32]; 1];
+    table.lookup(current_slot, &[index], &mut resolved)?;
+    if &resolved[0] != expected {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    Ok(())
+}
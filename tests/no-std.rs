@@ -0,0 +1,77 @@
+use std::process::Command;
+
+/// Checks the consumer crate at `tests/<name>`, which depends on this one.
+fn check_consumer(name: &str) {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let output = Command::new(cargo)
+        .args(["check", "--quiet", "--manifest-path"])
+        .arg(format!("{manifest_dir}/tests/{name}/Cargo.toml"))
+        .env("CARGO_TARGET_DIR", format!("{manifest_dir}/target/{name}"))
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_no_std_consumer_builds() {
+    check_consumer("no-std-consumer");
+}
+
+#[test]
+fn test_prelude_consumer_builds() {
+    check_consumer("prelude-consumer");
+}
+
+#[test]
+fn test_parse_only_builds_for_wasm() {
+    // Without the `program` feature pinocchio is not a dependency at all, so
+    // this build failing is how a pinocchio item leaking into the parsing
+    // code shows up.
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let sysroot = String::from_utf8(sysroot.stdout).unwrap();
+    assert!(
+        std::path::Path::new(sysroot.trim())
+            .join("lib/rustlib/wasm32-unknown-unknown")
+            .exists(),
+        "the wasm32-unknown-unknown target is not installed: \
+         rustup target add wasm32-unknown-unknown"
+    );
+
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = Command::new(cargo)
+        .args([
+            "check",
+            "--quiet",
+            "--lib",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--no-default-features",
+            "--features",
+            "parse-only",
+            "--manifest-path",
+        ])
+        .arg(format!("{manifest_dir}/Cargo.toml"))
+        .env(
+            "CARGO_TARGET_DIR",
+            format!("{manifest_dir}/target/parse-only"),
+        )
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
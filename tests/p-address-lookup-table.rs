@@ -1,239 +1,3798 @@
-use mollusk_svm::{account_store::AccountStore, program, result::Check, sysvar, Mollusk};
+mod common;
+
+use common::*;
+use mollusk_svm::{program, result::Check, sysvar, Mollusk};
+use p_address_lookup_table::{
+    instruction::{
+        create_lookup_table_data, derive_lookup_table_address, max_addable, plan_table_creation,
+        AccountAccess, ExtendInstructionBuilder, InstructionData, CLOSE_LOOKUP_TABLE_ACCOUNTS,
+        CREATE_LOOKUP_TABLE_ACCOUNTS, EXTEND_LOOKUP_TABLE_ACCOUNTS, MAX_ADDRESSES_PER_EXTEND,
+    },
+    interface::{
+        address_offset, LookupTableError, ProgramVersion, ALLOWLIST_HEADER_SIZE,
+        AUTHORITY_TAG_OFFSET, CLOSE_BURN_LAMPORTS, CLOSE_LOOKUP_TABLE,
+        CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT, FEATURE_PAYER_OPTIONAL_EXTEND,
+        FEATURE_TRANSFER_AUTHORITY, INCINERATOR_ID, LAST_EXTENDED_SLOT_OFFSET,
+        LAST_EXTENDED_SLOT_START_INDEX_OFFSET, LOOKUP_TABLE_DISCRIMINATOR, RENT_STATUS_LEN,
+        TOP_UP_RENT,
+    },
+    state::{serialize_new_lookup_table, LookupTable, LookupTableMeta, StateError},
+};
 use solana_account::Account;
 use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
 use solana_pubkey::Pubkey;
+use solana_rent::Rent;
 
-use solana_program::example_mocks::solana_sdk::system_program;
 use std::collections::HashMap;
-use std::sync::{LazyLock, Mutex};
 
-static ACCOUNTS: LazyLock<Mutex<InMemoryAccountStore>> =
-    LazyLock::new(|| Mutex::new(InMemoryAccountStore::default()));
+#[test]
+fn test_1_create_lookup_table() {
+    let table = TestTable::new();
+
+    let store = table.context.account_store.borrow();
+    let created_table = store.get(&table.key).unwrap();
+    assert_eq!(created_table.owner, PROGRAM_ID);
+    assert_eq!(created_table.data.len(), LOOKUP_TABLE_META_SIZE);
+    assert_eq!(
+        created_table.data[LAST_EXTENDED_SLOT_OFFSET..LAST_EXTENDED_SLOT_OFFSET + 8],
+        [0; 8]
+    );
+    drop(store);
+
+    assert_eq!(table.meta().authority, AUTHORITY.to_bytes());
+    table.assert_table(&[], false, false);
 
-#[derive(Default)]
-struct InMemoryAccountStore {
-    accounts: HashMap<Pubkey, Account>,
+    // Budget for a create into an empty account. The bulk of it is the
+    // `create_program_address` syscall (1,500 CU) and the single
+    // `CreateAccount` CPI (1,000 CU invoke cost plus the system program),
+    // followed by the slot hashes lookup and the instruction logs.
+    let authority = Pubkey::new_unique();
+    let (lookup_table, bump) =
+        Pubkey::find_program_address(&[authority.as_ref(), &0u64.to_le_bytes()], &PROGRAM_ID);
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .insert(lookup_table, Account::default());
+    let result = table.context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, PAYER, 0, bump),
+        &[Check::success()],
+    );
+    assert!(
+        result.compute_units_consumed < 10_000,
+        "create used {} CU",
+        result.compute_units_consumed
+    );
 }
 
-impl AccountStore for InMemoryAccountStore {
-    fn get_account(&self, pubkey: &Pubkey) -> Option<Account> {
-        self.accounts.get(pubkey).cloned()
+/// Prints the rows of the README compute unit table, e.g. with
+/// `cargo test --test p-address-lookup-table compute_units_report -- --nocapture`
+/// after rebuilding the program.
+#[test]
+fn test_compute_units_report() {
+    let mut rows = Vec::new();
+    let mut measure = |name: &str, context: &Context, instruction: &Instruction| {
+        let result = context.process_and_validate_instruction(instruction, &[Check::success()]);
+        rows.push(format!("| `{name}` | {} |", result.compute_units_consumed));
+    };
+
+    let mut table = TestTable::new();
+    let authority = Pubkey::new_unique();
+    let (lookup_table, bump) =
+        Pubkey::find_program_address(&[authority.as_ref(), &0u64.to_le_bytes()], &PROGRAM_ID);
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .insert(lookup_table, Account::default());
+    measure(
+        "CreateLookupTable",
+        &table.context,
+        &create_instruction(lookup_table, authority, PAYER, 0, bump),
+    );
+    measure(
+        "ExtendLookupTable",
+        &table.context,
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+    );
+    measure(
+        "DeactivateLookupTable",
+        &table.context,
+        &deactivate_instruction(table.key, AUTHORITY),
+    );
+    table.advance_clock(SLOT_HASHES_MAX_ENTRIES + 1);
+    measure(
+        "CloseLookupTable",
+        &table.context,
+        &close_instruction(table.key, AUTHORITY, PAYER),
+    );
+
+    let table = TestTable::with_addresses(&[Pubkey::new_unique()]);
+    measure(
+        "FreezeLookupTable",
+        &table.context,
+        &freeze_instruction(table.key, AUTHORITY),
+    );
+
+    for row in rows {
+        println!("{row}");
     }
+}
+
+#[test]
+fn test_2_extend_lookup_program() {
+    let table = TestTable::new();
 
-    fn store_account(&mut self, pubkey: Pubkey, account: Account) {
-        self.accounts.insert(pubkey, account);
+    let new_addresses = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let mut builder = ExtendInstructionBuilder::default();
+    for address in &new_addresses {
+        builder.push(address.to_bytes()).unwrap();
     }
+
+    let mut extend = extend_instruction(table.key, AUTHORITY, PAYER, &[]);
+    extend.data = builder.build();
+    table
+        .context
+        .process_and_validate_instruction(&extend, &[Check::success()]);
+
+    table.assert_table(&new_addresses, false, false);
+}
+
+#[test]
+fn test_extend_lookup_table_with_non_unique_addresses_spanning_batches() {
+    let [a, b, c] = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let mut table = TestTable::new();
+
+    table.extend(&[a, b]);
+    table.advance_clock(1);
+    table.extend(&[b, c]);
+
+    // Like the native program, extend appends without deduplicating, so `b`
+    // is stored twice. If this ever changes, indexes into existing tables
+    // would shift.
+    table.assert_table(&[a, b, b, c], false, false);
+    assert_eq!(table.addresses().len(), 4);
 }
 
-const PROGRAM_FILE_NAME: &str = "p_address_lookup_table";
+#[test]
+fn test_3_freeze_lookup_table() {
+    let new_addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let table = TestTable::with_addresses(&new_addresses);
+
+    table.context.process_and_validate_instruction(
+        &freeze_instruction(table.key, AUTHORITY),
+        &[Check::success()],
+    );
 
-const PROGRAM_ID: Pubkey = Pubkey::from_str_const("AddressLookupTab1e1111111111111111111111111");
-const AUTHORITY: Pubkey = Pubkey::from_str_const("Authority1111111111111111111111111111111111");
-const PAYER: Pubkey = Pubkey::from_str_const("Payer11111111111111111111111111111111111111");
+    table.assert_table(&new_addresses, true, false);
+}
 
 #[test]
-fn test_1_create_lookup_table() {
-    let mut accounts = ACCOUNTS.lock().unwrap();
+fn test_4_deactivate_lookup_table() {
+    let new_addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let table = TestTable::with_addresses(&new_addresses);
 
-    let recent_slot: u64 = 0;
-    let (lookup_table, bump) = Pubkey::find_program_address(
-        &[AUTHORITY.as_ref(), &recent_slot.to_le_bytes()],
-        &PROGRAM_ID,
+    table.context.process_and_validate_instruction(
+        &deactivate_instruction(table.key, AUTHORITY),
+        &[Check::success()],
     );
-    let (slot_key, slot_account) =
-        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
 
-    accounts.store_account(AUTHORITY, Account::default());
+    table.assert_table(&new_addresses, false, true);
+}
+
+#[test]
+fn test_5_close_lookup_table() {
+    let address = Pubkey::new_unique();
+    let mut table = TestTable::with_addresses(&[address]);
+    table.deactivate();
 
-    accounts.store_account(
-        PAYER,
+    let account = table
+        .context
+        .account_store
+        .borrow()
+        .get(&table.key)
+        .unwrap()
+        .clone();
+    let deactivated = lookup_table_view(&account);
+    assert_eq!(deactivated.meta().deactivation_slot, 0);
+    assert_eq!(deactivated.authority(), Some(&AUTHORITY.to_bytes()));
+    assert_eq!(deactivated.addresses(), [address.to_bytes()]);
+
+    let recipient = Pubkey::new_unique();
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .insert(recipient, Account::default());
+
+    // Move past the slot hashes window so the deactivation slot is gone.
+    table.advance_clock(SLOT_HASHES_MAX_ENTRIES + 1);
+    let table_lamports = table.lamports();
+    table.context.process_and_validate_instruction(
+        &close_instruction(table.key, AUTHORITY, recipient),
+        &[
+            Check::success(),
+            Check::account(&recipient).lamports(table_lamports).build(),
+            Check::account(&table.key).lamports(0).space(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_extend_lookup_table_rent_accounting() {
+    let rent = Rent::default();
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let initial_payer_lamports: u64 = 10_000_000_000;
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, initial_payer_lamports);
+
+    let mut expected_table_lamports = rent.minimum_balance(LOOKUP_TABLE_META_SIZE);
+    let mut expected_payer_lamports = initial_payer_lamports - expected_table_lamports;
+    assert_eq!(
+        lamports_of(&context, &lookup_table),
+        expected_table_lamports
+    );
+    assert_eq!(lamports_of(&context, &payer), expected_payer_lamports);
+
+    let mut addresses_len = 0;
+    for batch_len in [1, 7, 3, 20, 2] {
+        let new_addresses: Vec<Pubkey> = (0..batch_len).map(|_| Pubkey::new_unique()).collect();
+        addresses_len += batch_len;
+
+        let required = rent
+            .minimum_balance(LOOKUP_TABLE_META_SIZE + addresses_len * 32)
+            .saturating_sub(expected_table_lamports);
+        expected_table_lamports += required;
+        expected_payer_lamports -= required;
+
+        context.process_and_validate_instruction(
+            &extend_instruction(lookup_table, authority, payer, &new_addresses),
+            &[
+                Check::success(),
+                Check::account(&lookup_table)
+                    .lamports(expected_table_lamports)
+                    .space(LOOKUP_TABLE_META_SIZE + addresses_len * 32)
+                    .build(),
+                Check::account(&payer)
+                    .lamports(expected_payer_lamports)
+                    .build(),
+            ],
+        );
+    }
+
+    // The table ends up exactly rent-exempt for its final size and the payer
+    // was debited by exactly the sum of the top-ups.
+    assert_eq!(
+        lamports_of(&context, &lookup_table),
+        rent.minimum_balance(LOOKUP_TABLE_META_SIZE + addresses_len * 32)
+    );
+    assert_eq!(
+        initial_payer_lamports - lamports_of(&context, &payer),
+        lamports_of(&context, &lookup_table)
+    );
+}
+
+#[test]
+fn test_extend_over_funded_lookup_table_transfers_nothing() {
+    let rent = Rent::default();
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+
+    // A benefactor over-funds the table enough to cover the next extends.
+    let over_funded_lamports = rent.minimum_balance(LOOKUP_TABLE_META_SIZE + 10 * 32);
+    context
+        .account_store
+        .borrow_mut()
+        .get_mut(&lookup_table)
+        .unwrap()
+        .lamports = over_funded_lamports;
+    let payer_lamports = lamports_of(&context, &payer);
+
+    for batch_len in [2, 3, 4] {
+        let new_addresses: Vec<Pubkey> = (0..batch_len).map(|_| Pubkey::new_unique()).collect();
+        context.process_and_validate_instruction(
+            &extend_instruction(lookup_table, authority, payer, &new_addresses),
+            &[
+                Check::success(),
+                Check::account(&lookup_table)
+                    .lamports(over_funded_lamports)
+                    .build(),
+                Check::account(&payer).lamports(payer_lamports).build(),
+            ],
+        );
+    }
+
+    // Growing beyond the over-funded size only charges the difference.
+    let required = rent.minimum_balance(LOOKUP_TABLE_META_SIZE + 11 * 32) - over_funded_lamports;
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .lamports(over_funded_lamports + required)
+                .build(),
+            Check::account(&payer)
+                .lamports(payer_lamports - required)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_close_long_expired_lookup_table_without_slot_hashes() {
+    let mut table = TestTable::new();
+    let recipient = Pubkey::new_unique();
+
+    let deactivation_slot = 100;
+    let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    {
+        let mut accounts = table.context.account_store.borrow_mut();
+        accounts.insert(
+            table.key,
+            make_lookup_table_account(Some(AUTHORITY), &addresses, deactivation_slot),
+        );
+        accounts.insert(recipient, Account::default());
+    }
+
+    // Past the slot hashes window the syscall lookup no longer finds the
+    // deactivation slot, so the sysvar account is not needed.
+    let mut close = close_instruction(table.key, AUTHORITY, recipient);
+    close.accounts.pop();
+
+    table.advance_clock(deactivation_slot + SLOT_HASHES_MAX_ENTRIES + 1);
+    let table_lamports = table.lamports();
+    table.context.process_and_validate_instruction(
+        &close,
+        &[
+            Check::success(),
+            Check::account(&recipient).lamports(table_lamports).build(),
+            Check::account(&table.key).lamports(0).space(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_extend_read_only_lookup_table_leaves_state_untouched() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+
+    // A new slot makes extend update `last_extended_slot` before it reaches
+    // the writability check.
+    context.mollusk.warp_to_slot(10);
+    let snapshot = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .clone();
+
+    let mut extend = extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]);
+    extend.accounts[0] = AccountMeta::new_readonly(lookup_table, false);
+    context.process_and_validate_instruction(&extend, &[Check::err(ProgramError::Immutable)]);
+
+    // The failed instruction is rolled back as a whole.
+    assert_eq!(
+        context.account_store.borrow().get(&lookup_table).unwrap(),
+        &snapshot
+    );
+}
+
+#[test]
+fn test_extend_with_unfunded_payer_leaves_state_untouched() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let unfunded_payer = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(unfunded_payer, Account::default());
+
+    context.mollusk.warp_to_slot(10);
+    let snapshot = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .clone();
+
+    // The system program rejects the top-up with `ResultWithNegativeLamports`.
+    context.process_and_validate_instruction(
+        &extend_instruction(
+            lookup_table,
+            authority,
+            unfunded_payer,
+            &[Pubkey::new_unique()],
+        ),
+        &[Check::err(ProgramError::Custom(1))],
+    );
+
+    assert_eq!(
+        context.account_store.borrow().get(&lookup_table).unwrap(),
+        &snapshot
+    );
+    assert_eq!(lamports_of(&context, &unfunded_payer), 0);
+}
+
+#[test]
+fn test_lookup_table_meta_debug() {
+    let authority = Pubkey::new_unique();
+    let mut meta = LookupTableMeta {
+        deactivation_slot: u64::MAX,
+        last_extended_slot: 7,
+        last_extended_slot_start_index: 3,
+        authority_tag: 1,
+        authority: authority.to_bytes(),
+        version: 0,
+        deactivation_reason: 0,
+    };
+
+    let active = format!("{meta:?}");
+    assert!(active.contains(&format!("authority: {authority}")));
+    assert!(active.contains("frozen: false"));
+    assert!(active.contains("deactivation_slot: None"));
+    assert!(active.contains("last_extended_slot: 7"));
+    assert!(active.contains("last_extended_slot_start_index: 3"));
+
+    meta.authority_tag = 0;
+    meta.authority = [0; 32];
+    meta.deactivation_slot = 42;
+
+    let frozen = format!("{meta:?}");
+    assert!(frozen.contains("authority: <frozen>"));
+    assert!(frozen.contains("frozen: true"));
+    assert!(frozen.contains("deactivation_slot: Some(42)"));
+}
+
+#[test]
+fn test_truncated_lookup_table_account_is_rejected() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let lookup_table = Pubkey::new_unique();
+
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        payer,
         Account {
             lamports: 1_000_000_000,
             ..Account::default()
         },
     );
-
-    accounts.store_account(lookup_table, Account::default());
-    accounts.store_account(slot_key, slot_account);
-    accounts.store_account(
+    accounts.insert(
+        lookup_table,
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0; 20],
+            owner: PROGRAM_ID,
+            ..Account::default()
+        },
+    );
+    accounts.insert(
         program::keyed_account_for_system_program().0,
         program::keyed_account_for_system_program().1,
     );
 
-    let create_descriminator: u32 = 0;
-    let mut create_instruction_data = Vec::with_capacity(13);
-    create_instruction_data.extend_from_slice(&create_descriminator.to_le_bytes());
-    create_instruction_data.extend_from_slice(&recent_slot.to_le_bytes());
-    create_instruction_data.extend_from_slice(&[bump]);
-
-    let create_instruction = Instruction {
-        program_id: PROGRAM_ID,
-        accounts: vec![
-            AccountMeta::new(lookup_table, false),
-            AccountMeta::new_readonly(AUTHORITY, true),
-            AccountMeta::new(PAYER, true),
-            AccountMeta::new_readonly(slot_key, false),
-            AccountMeta::new_readonly(system_program::ID, false),
-        ],
-        data: create_instruction_data,
-    };
-
     let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
-    let context = mollusk.with_context(accounts.accounts.clone());
-    let result = context.process_and_validate_instruction(&create_instruction, &[Check::success()]);
+    let context = mollusk.with_context(accounts);
 
-    // Preserve the state of the created_table_account
-    accounts.store_account(
-        lookup_table,
-        result.get_account(&lookup_table).unwrap().clone(),
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::err(ProgramError::InvalidAccountData)],
     );
 }
 
 #[test]
-fn test_2_extend_lookup_program() {
-    let mut accounts = ACCOUNTS.lock().unwrap();
-    let recent_slot: u64 = 0;
+fn test_extend_last_address_sets_start_index_255() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
 
-    let (lookup_table, _bump) = Pubkey::find_program_address(
-        &[AUTHORITY.as_ref(), &recent_slot.to_le_bytes()],
-        &PROGRAM_ID,
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+
+    context.mollusk.warp_to_slot(1);
+    let new_addresses: Vec<Pubkey> = (0..255).map(|_| Pubkey::new_unique()).collect();
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &new_addresses),
+        &[Check::success()],
     );
+    assert_eq!(addresses_len_of(&context, &lookup_table), 255);
 
-    let extend_descriminator: u32 = 2;
-    let address_len: usize = 3;
-    let new_addresses = [
+    context.mollusk.warp_to_slot(2);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+
+    let meta = meta_of(&context, &lookup_table);
+    assert_eq!(meta.last_extended_slot, 2);
+    assert_eq!(meta.last_extended_slot_start_index, 255);
+    assert_eq!(addresses_len_of(&context, &lookup_table), 256);
+
+    assert_eq!(meta.active_addresses_len(2, 256), 255);
+    assert_eq!(meta.active_addresses_len(3, 256), 256);
+}
+
+#[test]
+fn test_extend_across_slots_tracks_latest_batch() {
+    let mut table = TestTable::new();
+    let mut len = 0;
+
+    // (slot, batch size, start index): the first batch lands in slot 0, the slot a new
+    // table's `last_extended_slot` already holds, and slot 5 is extended
+    // twice.
+    for (slot, batch, start_index) in [(0, 2, 0), (5, 3, 2), (5, 1, 2), (6, 4, 6)] {
+        table.context.mollusk.warp_to_slot(slot);
+        let addresses: Vec<Pubkey> = (0..batch).map(|_| Pubkey::new_unique()).collect();
+        table.extend(&addresses);
+        len += batch;
+
+        let meta = meta_of(&table.context, &table.key);
+        assert_eq!(meta.last_extended_slot, slot);
+        assert_eq!(meta.last_extended_slot_start_index, start_index);
+        assert_eq!(addresses_len_of(&table.context, &table.key), len);
+
+        // Only this slot's addresses are still warming up.
+        assert_eq!(meta.active_addresses_len(slot, len), start_index as usize);
+        assert_eq!(meta.active_addresses_len(slot + 1, len), len);
+    }
+}
+
+#[test]
+fn test_create_lookup_table_with_max_slot() {
+    for recent_slot in [u64::MAX - 1, u64::MAX] {
+        let authority = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let (lookup_table, bump) = Pubkey::find_program_address(
+            &[authority.as_ref(), &recent_slot.to_le_bytes()],
+            &PROGRAM_ID,
+        );
+        let (slot_key, slot_account) = populated_slot_hashes(&[recent_slot]);
+
+        let mut accounts = HashMap::new();
+        accounts.insert(authority, Account::default());
+        accounts.insert(
+            payer,
+            Account {
+                lamports: 1_000_000_000,
+                ..Account::default()
+            },
+        );
+        accounts.insert(lookup_table, Account::default());
+        accounts.insert(slot_key, slot_account);
+        accounts.insert(
+            program::keyed_account_for_system_program().0,
+            program::keyed_account_for_system_program().1,
+        );
+
+        let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+        let context = mollusk.with_context(accounts);
+        context.process_and_validate_instruction(
+            &create_instruction(lookup_table, authority, payer, recent_slot, bump),
+            &[
+                Check::success(),
+                Check::account(&lookup_table)
+                    .owner(&PROGRAM_ID)
+                    .space(LOOKUP_TABLE_META_SIZE)
+                    .build(),
+            ],
+        );
+    }
+}
+
+#[test]
+fn test_collect_addresses_deduplicates_across_tables() {
+    let authority = Pubkey::new_unique();
+    let other_authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, first_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    let second_table = add_lookup_table(&context, other_authority, payer);
+
+    let [first, shared, second] = [
         Pubkey::new_unique(),
         Pubkey::new_unique(),
         Pubkey::new_unique(),
     ];
-    let mut extend_instruction_data = Vec::with_capacity(4 + 8 + new_addresses.len() * 32);
-    extend_instruction_data.extend_from_slice(&extend_descriminator.to_le_bytes());
-    extend_instruction_data.extend_from_slice(&address_len.to_le_bytes());
-    extend_instruction_data.extend_from_slice(&new_addresses[0].as_ref());
-    extend_instruction_data.extend_from_slice(&new_addresses[1].as_ref());
-    extend_instruction_data.extend_from_slice(&new_addresses[2].as_ref());
-
-    let extend_instruction = Instruction {
-        program_id: PROGRAM_ID,
-        accounts: vec![
-            AccountMeta::new(lookup_table, false),
-            AccountMeta::new_readonly(AUTHORITY, true),
-            AccountMeta::new(PAYER, true),
-            AccountMeta::new_readonly(system_program::id(), false),
-        ],
-        data: extend_instruction_data,
-    };
+    context.process_and_validate_instruction(
+        &extend_instruction(first_table, authority, payer, &[first, shared]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &extend_instruction(second_table, other_authority, payer, &[shared, second]),
+        &[Check::success()],
+    );
 
-    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
-    let context = mollusk.with_context(accounts.accounts.clone());
-    let result = context.process_and_validate_instruction(&extend_instruction, &[Check::success()]);
-    accounts.store_account(
-        lookup_table,
-        result.get_account(&lookup_table).unwrap().clone(),
+    let expected: Vec<u8> = [first, shared, second]
+        .iter()
+        .flat_map(|address| address.to_bytes())
+        .collect();
+    context.process_and_validate_instruction(
+        &collect_addresses_instruction(&[first_table, second_table]),
+        &[Check::success(), Check::return_data(&expected)],
     );
 }
 
 #[test]
-fn test_3_freeze_lookup_table() {
-    let accounts = ACCOUNTS.lock().unwrap();
+fn test_collect_addresses_exceeding_return_data_fails() {
+    let authority = Pubkey::new_unique();
+    let other_authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
 
-    let recent_slot: u64 = 0;
-    let (lookup_table, _bump) = Pubkey::find_program_address(
-        &[AUTHORITY.as_ref(), &recent_slot.to_le_bytes()],
-        &PROGRAM_ID,
-    );
+    let (context, first_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    let second_table = add_lookup_table(&context, other_authority, payer);
 
-    let freeze_descriminator: u32 = 1;
-    let mut freeze_instruction_data = Vec::with_capacity(4);
-    freeze_instruction_data.extend_from_slice(&freeze_descriminator.to_le_bytes());
+    let first_addresses: Vec<Pubkey> = (0..20).map(|_| Pubkey::new_unique()).collect();
+    let second_addresses: Vec<Pubkey> = (0..13).map(|_| Pubkey::new_unique()).collect();
+    context.process_and_validate_instruction(
+        &extend_instruction(first_table, authority, payer, &first_addresses),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &extend_instruction(second_table, other_authority, payer, &second_addresses),
+        &[Check::success()],
+    );
 
-    let freeze_instruction = Instruction {
-        program_id: PROGRAM_ID,
-        accounts: vec![
-            AccountMeta::new(lookup_table, false),
-            AccountMeta::new_readonly(AUTHORITY, true),
-        ],
-        data: freeze_instruction_data,
-    };
+    // 33 distinct addresses don't fit in 1024 bytes of return data.
+    context.process_and_validate_instruction(
+        &collect_addresses_instruction(&[first_table, second_table]),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
 
-    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
-    let context = mollusk.with_context(accounts.accounts.clone());
+#[test]
+fn test_extend_lookup_table_non_owner() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
 
-    context.process_and_validate_instruction(&freeze_instruction, &[Check::success()]);
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context
+        .account_store
+        .borrow_mut()
+        .get_mut(&lookup_table)
+        .unwrap()
+        .owner = payer;
 
-    // here we aren't passing the state of the table, its intentional, because we cant deactivate frozen account
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::InvalidAccountOwner)],
+    );
 }
 
 #[test]
-fn test_4_deactivate_lookup_table() {
-    let mut accounts = ACCOUNTS.lock().unwrap();
+fn test_extend_misaligned_lookup_table_is_rejected() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
 
-    let recent_slot: u64 = 0;
-    let (lookup_table, _bump) = Pubkey::find_program_address(
-        &[AUTHORITY.as_ref(), &recent_slot.to_le_bytes()],
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context
+        .account_store
+        .borrow_mut()
+        .get_mut(&lookup_table)
+        .unwrap()
+        .data
+        .resize(LOOKUP_TABLE_META_SIZE + 40, 0);
+
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+/// Creates a table from the clock at `current_slot`, with the slot hashes
+/// holding `recent_slots`, and checks it is derived from `derivation_slot`.
+fn check_create_from_clock(current_slot: u64, recent_slots: &[u64], derivation_slot: u64) {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &derivation_slot.to_le_bytes()],
         &PROGRAM_ID,
     );
+    let (slot_key, slot_account) = populated_slot_hashes(recent_slots);
 
-    let deactivate_descriminator: u32 = 3;
-    let mut deactivate_instruction_data = Vec::with_capacity(4);
-    deactivate_instruction_data.extend_from_slice(&deactivate_descriminator.to_le_bytes());
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        payer,
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+    accounts.insert(lookup_table, Account::default());
+    accounts.insert(slot_key, slot_account);
+    accounts.insert(
+        program::keyed_account_for_system_program().0,
+        program::keyed_account_for_system_program().1,
+    );
 
-    let deactivate_instruction = Instruction {
-        program_id: PROGRAM_ID,
-        accounts: vec![
-            AccountMeta::new(lookup_table, false),
-            AccountMeta::new_readonly(AUTHORITY, true),
-        ],
-        data: deactivate_instruction_data,
-    };
+    let mut mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    mollusk.warp_to_slot(current_slot);
+    let context = mollusk.with_context(accounts);
 
-    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
-    let context = mollusk.with_context(accounts.accounts.clone());
+    let create_from_clock_descriminator: u32 = 6;
+    let mut create = create_instruction(lookup_table, authority, payer, 0, 0);
+    create.data = create_from_clock_descriminator.to_le_bytes().to_vec();
 
-    let result =
-        context.process_and_validate_instruction(&deactivate_instruction, &[Check::success()]);
-    accounts.store_account(
-        lookup_table,
-        result.get_account(&lookup_table).unwrap().clone(),
+    let mut expected_return_data = lookup_table.to_bytes().to_vec();
+    expected_return_data.extend_from_slice(&derivation_slot.to_le_bytes());
+    expected_return_data.push(bump);
+
+    context.process_and_validate_instruction(
+        &create,
+        &[
+            Check::success(),
+            Check::return_data(&expected_return_data),
+            Check::account(&lookup_table)
+                .owner(&PROGRAM_ID)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+        ],
     );
 }
 
 #[test]
-fn test_5_close_lookup_table() {
-    let mut accounts = ACCOUNTS.lock().unwrap();
+fn test_create_lookup_table_from_clock() {
+    // The program derives from the most recent slot in the slot hashes,
+    // here the slot right before the current one.
+    check_create_from_clock(10, &[9, 8], 9);
+}
 
-    let recent_slot: u64 = 0;
-    let (lookup_table, _bump) = Pubkey::find_program_address(
-        &[AUTHORITY.as_ref(), &recent_slot.to_le_bytes()],
-        &PROGRAM_ID,
+#[test]
+fn test_create_lookup_table_from_clock_after_skipped_slot() {
+    // Slot 9 was skipped, so it never entered the slot hashes and the table
+    // is derived from slot 8 instead.
+    check_create_from_clock(10, &[8, 7], 8);
+}
+
+#[test]
+fn test_create_and_extend_with_authority_as_payer() {
+    let rent = Rent::default();
+    let authority = Pubkey::new_unique();
+    let initial_lamports: u64 = 10_000_000_000;
+
+    let (context, lookup_table) = create_lookup_table(authority, authority, initial_lamports);
+    let create_lamports = rent.minimum_balance(LOOKUP_TABLE_META_SIZE);
+    assert_eq!(
+        lamports_of(&context, &authority),
+        initial_lamports - create_lamports
     );
 
-    let (slot_key, _slot_account) =
-        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+    let extend_lamports = rent.minimum_balance(LOOKUP_TABLE_META_SIZE + 2 * 32) - create_lamports;
+    context.process_and_validate_instruction(
+        &extend_instruction(
+            lookup_table,
+            authority,
+            authority,
+            &[Pubkey::new_unique(), Pubkey::new_unique()],
+        ),
+        &[
+            Check::success(),
+            Check::account(&authority)
+                .lamports(initial_lamports - create_lamports - extend_lamports)
+                .build(),
+            Check::account(&lookup_table)
+                .lamports(create_lamports + extend_lamports)
+                .build(),
+        ],
+    );
+}
 
-    let recipient = Pubkey::new_unique();
-    accounts.store_account(recipient, Account::default());
+#[test]
+fn test_close_lookup_table_where_recipient_equals_authority() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
 
-    let mut tweaked_meta = accounts.get_account(&lookup_table).unwrap();
-    tweaked_meta.data[4] = 42; // Tweaking the deactivation slot so it wont be found in the recent slots
-    accounts.store_account(lookup_table, tweaked_meta);
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context
+        .account_store
+        .borrow_mut()
+        .get_mut(&authority)
+        .unwrap()
+        .lamports = 5_000;
 
-    let close_descriminator: u32 = 4;
-    let mut close_instruction_data = Vec::with_capacity(4);
-    close_instruction_data.extend_from_slice(&close_descriminator.to_le_bytes());
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
 
-    let close_instruction = Instruction {
-        program_id: PROGRAM_ID,
-        accounts: vec![
-            AccountMeta::new(lookup_table, false),
-            AccountMeta::new_readonly(AUTHORITY, true),
-            AccountMeta::new(recipient, false),
-            AccountMeta::new_readonly(slot_key, false),
+    let table_lamports = lamports_of(&context, &lookup_table);
+    context.process_and_validate_instruction(
+        &close_instruction(lookup_table, authority, authority),
+        &[
+            Check::success(),
+            Check::account(&authority)
+                .lamports(5_000 + table_lamports)
+                .build(),
+            Check::account(&lookup_table).lamports(0).build(),
         ],
-        data: close_instruction_data,
-    };
-    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
-    let context = mollusk.with_context(accounts.accounts.clone());
+    );
+}
+
+#[test]
+fn test_close_with_payer_as_recipient() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let initial_lamports: u64 = 10_000_000_000;
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, initial_lamports);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    // Everything the payer put into the table comes back to it.
+    context.process_and_validate_instruction(
+        &close_instruction(lookup_table, authority, payer),
+        &[
+            Check::success(),
+            Check::account(&payer).lamports(initial_lamports).build(),
+            Check::account(&lookup_table).lamports(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_create_with_lookup_table_as_payer_fails() {
+    let authority = Pubkey::new_unique();
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    let (slot_key, slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
 
-    context.process_and_validate_instruction(&close_instruction, &[Check::success()]);
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        lookup_table,
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+    accounts.insert(slot_key, slot_account);
+    accounts.insert(
+        program::keyed_account_for_system_program().0,
+        program::keyed_account_for_system_program().1,
+    );
+
+    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    let context = mollusk.with_context(accounts);
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, lookup_table, recent_slot, bump),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_extend_with_lookup_table_as_payer_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(
+            lookup_table,
+            authority,
+            lookup_table,
+            &[Pubkey::new_unique()],
+        ),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_close_with_lookup_table_as_recipient_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    context.process_and_validate_instruction(
+        &close_instruction(lookup_table, authority, lookup_table),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_create_lookup_table_drains_exactly_funded_payer() {
+    let rent = Rent::default();
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let required_lamports = rent.minimum_balance(LOOKUP_TABLE_META_SIZE);
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, required_lamports);
+
+    assert_eq!(lamports_of(&context, &payer), 0);
+    assert_eq!(lamports_of(&context, &lookup_table), required_lamports);
+}
+
+#[test]
+fn test_freeze_deactivating_lookup_table_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+
+    context.process_and_validate_instruction(
+        &freeze_instruction(lookup_table, authority),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_freeze_fully_deactivated_lookup_table_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    context.process_and_validate_instruction(
+        &freeze_instruction(lookup_table, authority),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_extend_instruction_builder_rejects_more_than_max_addresses() {
+    let mut builder = ExtendInstructionBuilder::default();
+    for _ in 0..256 {
+        builder.push(Pubkey::new_unique().to_bytes()).unwrap();
+    }
+    assert!(builder.push(Pubkey::new_unique().to_bytes()).is_err());
+
+    let data = builder.build();
+    assert_eq!(data.len(), 4 + 8 + 256 * 32);
+    assert_eq!(data[4..12], 256u64.to_le_bytes());
+}
+
+#[test]
+fn test_instruction_data_round_trip() {
+    let instructions = [
+        InstructionData::Create {
+            recent_slot: 123,
+            bump: 254,
+        },
+        InstructionData::Freeze,
+        InstructionData::Extend {
+            addresses: vec![
+                Pubkey::new_unique().to_bytes(),
+                Pubkey::new_unique().to_bytes(),
+            ],
+        },
+        InstructionData::Extend { addresses: vec![] },
+        InstructionData::Deactivate,
+        InstructionData::Close,
+    ];
+    for instruction in instructions {
+        let data = instruction.serialize();
+        assert_eq!(InstructionData::try_from(data.as_slice()), Ok(instruction));
+    }
+
+    // Same bytes as the hand-built instruction the native program expects.
+    let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+    assert_eq!(
+        InstructionData::Extend {
+            addresses: addresses.iter().map(Pubkey::to_bytes).collect(),
+        }
+        .serialize(),
+        [
+            &2u32.to_le_bytes()[..],
+            &2u64.to_le_bytes(),
+            addresses[0].as_ref(),
+            addresses[1].as_ref(),
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn test_instruction_data_rejects_what_it_cannot_hold() {
+    let mut close_with_flags = InstructionData::Close.serialize();
+    close_with_flags.push(CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT);
+    let mut deactivate_with_reason = InstructionData::Deactivate.serialize();
+    deactivate_with_reason.push(1);
+    let mut truncated_extend = InstructionData::Extend {
+        addresses: vec![Pubkey::new_unique().to_bytes()],
+    }
+    .serialize();
+    truncated_extend.pop();
+
+    for data in [
+        close_with_flags,
+        deactivate_with_reason,
+        truncated_extend,
+        TOP_UP_RENT.to_le_bytes().to_vec(),
+        vec![0; 3],
+    ] {
+        assert_eq!(
+            InstructionData::try_from(data.as_slice()),
+            Err(pinocchio::program_error::ProgramError::InvalidInstructionData)
+        );
+    }
+}
+
+#[test]
+fn test_mutations_bump_lookup_table_version() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    assert_eq!(meta_of(&context, &lookup_table).version, 0);
+
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    assert_eq!(meta_of(&context, &lookup_table).version, 1);
+
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    assert_eq!(meta_of(&context, &lookup_table).version, 2);
+}
+
+#[test]
+fn test_exhausted_version_only_rejects_checked_extend() {
+    use p_address_lookup_table::interface::VERSION_OFFSET;
+
+    let mut table = TestTable::with_addresses(&[Pubkey::new_unique()]);
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .get_mut(&table.key)
+        .unwrap()
+        .data[VERSION_OFFSET] = u8::MAX - 1;
+
+    table.extend(&[Pubkey::new_unique()]);
+    assert_eq!(table.meta().version, u8::MAX);
+
+    // The version stays at u8::MAX rather than wrap back to a value a client
+    // may hold, so a checked extend can no longer prove the table unchanged.
+    let exhausted = ProgramError::Custom(LookupTableError::VersionExhausted as u32);
+    table.context.process_and_validate_instruction(
+        &extend_checked_instruction(
+            table.key,
+            AUTHORITY,
+            PAYER,
+            u8::MAX,
+            &[Pubkey::new_unique()],
+        ),
+        &[Check::err(exhausted)],
+    );
+
+    // Everything else keeps working, down to closing the table.
+    table.extend(&[Pubkey::new_unique()]);
+    table.deactivate();
+    assert_eq!(table.meta().version, u8::MAX);
+    assert_eq!(table.addresses().len(), 3);
+
+    let recipient = Pubkey::new_unique();
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .insert(recipient, Account::default());
+    table.advance_clock(SLOT_HASHES_MAX_ENTRIES + 1);
+    table.context.process_and_validate_instruction(
+        &close_instruction(table.key, AUTHORITY, recipient),
+        &[Check::success()],
+    );
+}
+
+#[test]
+fn test_extend_checked_with_matching_version() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+
+    context.process_and_validate_instruction(
+        &extend_checked_instruction(lookup_table, authority, payer, 0, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &extend_checked_instruction(lookup_table, authority, payer, 1, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+
+    assert_eq!(meta_of(&context, &lookup_table).version, 2);
+    assert_eq!(addresses_len_of(&context, &lookup_table), 2);
+}
+
+#[test]
+fn test_extend_checked_with_stale_version_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    let snapshot = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .clone();
+
+    context.process_and_validate_instruction(
+        &extend_checked_instruction(lookup_table, authority, payer, 0, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+
+    assert_eq!(
+        context.account_store.borrow().get(&lookup_table).unwrap(),
+        &snapshot
+    );
+}
+
+#[test]
+fn test_create_with_on_curve_bump_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, _) = create_lookup_table(authority, payer, 10_000_000_000);
+
+    let recent_slot: u64 = 0;
+    let new_authority = Pubkey::new_unique();
+    let on_curve_bump = (0..=u8::MAX)
+        .rev()
+        .find(|bump| {
+            Pubkey::create_program_address(
+                &[new_authority.as_ref(), &recent_slot.to_le_bytes(), &[*bump]],
+                &PROGRAM_ID,
+            )
+            .is_err()
+        })
+        .expect("some bump derives an on-curve address");
+    let (lookup_table, _) = Pubkey::find_program_address(
+        &[new_authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    context
+        .account_store
+        .borrow_mut()
+        .insert(lookup_table, Account::default());
+
+    context.process_and_validate_instruction(
+        &create_instruction(
+            lookup_table,
+            new_authority,
+            payer,
+            recent_slot,
+            on_curve_bump,
+        ),
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+}
+
+#[test]
+fn test_create_with_non_matching_bump_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, _) = create_lookup_table(authority, payer, 10_000_000_000);
+
+    let recent_slot: u64 = 0;
+    let new_authority = Pubkey::new_unique();
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[new_authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    // A bump that derives a valid address, just not the one passed in.
+    let other_bump = (0..bump)
+        .rev()
+        .find(|bump| {
+            Pubkey::create_program_address(
+                &[new_authority.as_ref(), &recent_slot.to_le_bytes(), &[*bump]],
+                &PROGRAM_ID,
+            )
+            .is_ok()
+        })
+        .expect("some lower bump derives an off-curve address");
+    context
+        .account_store
+        .borrow_mut()
+        .insert(lookup_table, Account::default());
+
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, new_authority, payer, recent_slot, other_bump),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_authority_transfer_after_create() {
+    let payer = Pubkey::new_unique();
+    let (context, lookup_table) = create_lookup_table(AUTHORITY, payer, 10_000_000_000);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(AUTHORITY2, Account::default());
+
+    // Trailing accounts are ignored, as in the other instructions.
+    let mut transfer = transfer_authority_instruction(lookup_table, AUTHORITY, AUTHORITY2);
+    transfer
+        .accounts
+        .push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+    context.process_and_validate_instruction(&transfer, &[Check::success()]);
+    assert_eq!(
+        meta_of(&context, &lookup_table).authority,
+        AUTHORITY2.to_bytes()
+    );
+
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, AUTHORITY2, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, AUTHORITY, payer, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+
+    context.process_and_validate_instruction(
+        &freeze_instruction(lookup_table, AUTHORITY2),
+        &[Check::success()],
+    );
+    assert_eq!(meta_of(&context, &lookup_table).authority_tag, 0);
+}
+
+#[test]
+fn test_derive_lookup_table_address_matches_program() {
+    let payer = Pubkey::new_unique();
+    let (context, _) = create_lookup_table(Pubkey::new_unique(), payer, 10_000_000_000);
+
+    for authority in [
+        AUTHORITY,
+        AUTHORITY2,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ] {
+        let recent_slot: u64 = 0;
+        let (expected, bump) = Pubkey::find_program_address(
+            &[authority.as_ref(), &recent_slot.to_le_bytes()],
+            &PROGRAM_ID,
+        );
+
+        let derived = Pubkey::new_from_array(derive_lookup_table_address(
+            &authority.to_bytes(),
+            recent_slot,
+            bump,
+            &PROGRAM_ID.to_bytes(),
+        ));
+        assert_eq!(derived, expected);
+
+        {
+            let mut accounts = context.account_store.borrow_mut();
+            accounts.entry(authority).or_default();
+            accounts.insert(derived, Account::default());
+        }
+        context.process_and_validate_instruction(
+            &create_instruction(derived, authority, payer, recent_slot, bump),
+            &[Check::success()],
+        );
+    }
+}
+
+#[test]
+fn test_create_with_empty_slot_hashes_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    let (slot_key, slot_account) = populated_slot_hashes(&[]);
+
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        payer,
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+    accounts.insert(lookup_table, Account::default());
+    accounts.insert(slot_key, slot_account);
+    accounts.insert(
+        program::keyed_account_for_system_program().0,
+        program::keyed_account_for_system_program().1,
+    );
+
+    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    let context = mollusk.with_context(accounts);
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, payer, recent_slot, bump),
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+}
+
+#[test]
+fn test_close_deactivated_lookup_table_with_empty_slot_hashes() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+
+    // Still well inside the slot hashes window, but with no entries the
+    // deactivation slot cannot be found, which the native program also
+    // treats as fully deactivated.
+    context.mollusk.warp_to_slot(10);
+    let (slot_key, slot_account) = populated_slot_hashes(&[]);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(slot_key, slot_account);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(recipient, Account::default());
+
+    let table_lamports = lamports_of(&context, &lookup_table);
+    context.process_and_validate_instruction(
+        &close_instruction(lookup_table, authority, recipient),
+        &[
+            Check::success(),
+            Check::account(&recipient).lamports(table_lamports).build(),
+            Check::account(&lookup_table).lamports(0).space(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_lookup_table_meta_deactivation_countdown() {
+    let mut meta = LookupTableMeta {
+        deactivation_slot: u64::MAX,
+        last_extended_slot: 0,
+        last_extended_slot_start_index: 0,
+        authority_tag: 1,
+        authority: AUTHORITY.to_bytes(),
+        version: 0,
+        deactivation_reason: 0,
+    };
+    assert_eq!(meta.deactivation_countdown(100), None);
+
+    meta.deactivation_slot = 100;
+    assert_eq!(meta.deactivation_countdown(100), Some(513));
+    assert_eq!(meta.deactivation_countdown(101), Some(512));
+    assert_eq!(meta.deactivation_countdown(100 + 512), Some(1));
+    assert_eq!(meta.deactivation_countdown(100 + 513), Some(0));
+    assert_eq!(meta.deactivation_countdown(u64::MAX - 1), Some(0));
+}
+
+#[test]
+fn test_top_up_rent_funds_under_funded_lookup_table() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let rent = Rent::default();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+
+    // Simulate a rent increase by draining part of the table's balance.
+    let table_size = LOOKUP_TABLE_META_SIZE + 32;
+    let rent_exempt_lamports = rent.minimum_balance(table_size);
+    context
+        .account_store
+        .borrow_mut()
+        .get_mut(&lookup_table)
+        .unwrap()
+        .lamports = rent_exempt_lamports / 2;
+
+    // Anyone can top up, so a stranger pays without the authority signing.
+    let stranger = Pubkey::new_unique();
+    context.account_store.borrow_mut().insert(
+        stranger,
+        Account {
+            lamports: 1_000_000_000,
+            ..Account::default()
+        },
+    );
+
+    context.process_and_validate_instruction(
+        &top_up_rent_instruction(lookup_table, stranger),
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .lamports(rent_exempt_lamports)
+                .space(table_size)
+                .build(),
+            Check::account(&stranger)
+                .lamports(1_000_000_000 - (rent_exempt_lamports - rent_exempt_lamports / 2))
+                .build(),
+        ],
+    );
+
+    // Already rent exempt, so topping up again is a no-op. Trailing accounts
+    // are ignored, as in the other instructions.
+    let mut top_up = top_up_rent_instruction(lookup_table, stranger);
+    top_up
+        .accounts
+        .push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+    context.process_and_validate_instruction(
+        &top_up,
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .lamports(rent_exempt_lamports)
+                .build(),
+        ],
+    );
+}
+
+fn rent_status(current_lamports: u64, required_lamports: u64, is_exempt: bool) -> Vec<u8> {
+    let mut status = Vec::with_capacity(RENT_STATUS_LEN);
+    status.extend_from_slice(&current_lamports.to_le_bytes());
+    status.extend_from_slice(&required_lamports.to_le_bytes());
+    status.push(is_exempt as u8);
+    status
+}
+
+#[test]
+fn test_rent_status_of_rent_exempt_lookup_table() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+
+    let required_lamports = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE + 32);
+    context.process_and_validate_instruction(
+        &rent_status_instruction(lookup_table),
+        &[
+            Check::success(),
+            Check::return_data(&rent_status(required_lamports, required_lamports, true)),
+        ],
+    );
+}
+
+#[test]
+fn test_rent_status_of_under_funded_lookup_table() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+
+    // Simulate a rent increase by draining part of the table's balance.
+    let required_lamports = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE);
+    context
+        .account_store
+        .borrow_mut()
+        .get_mut(&lookup_table)
+        .unwrap()
+        .lamports = required_lamports - 1;
+
+    context.process_and_validate_instruction(
+        &rent_status_instruction(lookup_table),
+        &[
+            Check::success(),
+            Check::return_data(&rent_status(
+                required_lamports - 1,
+                required_lamports,
+                false,
+            )),
+        ],
+    );
+
+    // Topping up brings it back.
+    context.process_and_validate_instruction(
+        &top_up_rent_instruction(lookup_table, payer),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &rent_status_instruction(lookup_table),
+        &[
+            Check::success(),
+            Check::return_data(&rent_status(required_lamports, required_lamports, true)),
+        ],
+    );
+}
+
+#[test]
+fn test_rent_status_of_empty_account() {
+    let table = TestTable::new();
+    let closed = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    table.context.account_store.borrow_mut().extend([
+        (closed, Account::new(0, 0, &PROGRAM_ID)),
+        (other, Account::default()),
+    ]);
+
+    // A table closed earlier in the transaction keeps this program as owner,
+    // but without data it is no longer a table.
+    table.context.process_and_validate_instruction(
+        &rent_status_instruction(closed),
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+
+    // An empty account of another program is not a closed table.
+    table.context.process_and_validate_instruction(
+        &rent_status_instruction(other),
+        &[Check::err(ProgramError::InvalidAccountOwner)],
+    );
+}
+
+#[test]
+fn test_many_lookup_tables_under_one_authority() {
+    const TABLES: u64 = 50;
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let payer_lamports = 100_000_000_000;
+
+    // Newest slot first, as in the sysvar.
+    let recent_slots: Vec<u64> = (1..=TABLES).rev().collect();
+    let (slot_key, slot_account) = populated_slot_hashes(&recent_slots);
+
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        payer,
+        Account {
+            lamports: payer_lamports,
+            ..Account::default()
+        },
+    );
+    accounts.insert(recipient, Account::default());
+    accounts.insert(slot_key, slot_account);
+    accounts.insert(
+        program::keyed_account_for_system_program().0,
+        program::keyed_account_for_system_program().1,
+    );
+
+    let mut mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    mollusk.warp_to_slot(TABLES + 1);
+    let mut context = mollusk.with_context(accounts);
+
+    let lookup_tables: Vec<Pubkey> = recent_slots
+        .iter()
+        .map(|recent_slot| {
+            let (lookup_table, bump) = Pubkey::find_program_address(
+                &[authority.as_ref(), &recent_slot.to_le_bytes()],
+                &PROGRAM_ID,
+            );
+            context
+                .account_store
+                .borrow_mut()
+                .insert(lookup_table, Account::default());
+            context.process_and_validate_instruction(
+                &create_instruction(lookup_table, authority, payer, *recent_slot, bump),
+                &[Check::success()],
+            );
+            lookup_table
+        })
+        .collect();
+
+    let mut expected_addresses = vec![Vec::new(); lookup_tables.len()];
+    for round in 1..=3 {
+        for (i, lookup_table) in lookup_tables.iter().enumerate() {
+            if i % round != 0 {
+                continue;
+            }
+            let new_addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+            context.process_and_validate_instruction(
+                &extend_instruction(*lookup_table, authority, payer, &new_addresses),
+                &[Check::success()],
+            );
+            expected_addresses[i].extend(new_addresses);
+        }
+    }
+
+    let (closing, live): (Vec<_>, Vec<_>) = lookup_tables
+        .iter()
+        .zip(&expected_addresses)
+        .enumerate()
+        .partition(|(i, _)| i % 2 == 0);
+
+    for (_, (lookup_table, _)) in &closing {
+        context.process_and_validate_instruction(
+            &deactivate_instruction(**lookup_table, authority),
+            &[Check::success()],
+        );
+    }
+    context
+        .mollusk
+        .warp_to_slot(TABLES + 1 + SLOT_HASHES_MAX_ENTRIES + 1);
+    for (_, (lookup_table, _)) in &closing {
+        context.process_and_validate_instruction(
+            &close_instruction(**lookup_table, authority, recipient),
+            &[
+                Check::success(),
+                Check::account(lookup_table).lamports(0).space(0).build(),
+            ],
+        );
+    }
+
+    for (_, (lookup_table, addresses)) in &live {
+        let meta = meta_of(&context, lookup_table);
+        assert_eq!(meta.deactivation_slot, u64::MAX);
+        assert_eq!(meta.authority, authority.to_bytes());
+
+        let store = context.account_store.borrow();
+        let data = &store.get(lookup_table).unwrap().data;
+        let stored: Vec<Pubkey> = data[LOOKUP_TABLE_META_SIZE..]
+            .chunks_exact(32)
+            .map(|chunk| Pubkey::try_from(chunk).unwrap())
+            .collect();
+        assert_eq!(&stored, *addresses);
+    }
+
+    let store = context.account_store.borrow();
+    let total_lamports: u64 = lookup_tables
+        .iter()
+        .chain([&payer, &recipient])
+        .map(|key| store.get(key).map_or(0, |account| account.lamports))
+        .sum();
+    assert_eq!(total_lamports, payer_lamports);
+}
+
+#[test]
+fn test_addresses_ready_excludes_addresses_extended_this_slot() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.mollusk.warp_to_slot(10);
+    context.process_and_validate_instruction(
+        &extend_instruction(
+            lookup_table,
+            authority,
+            payer,
+            &[Pubkey::new_unique(), Pubkey::new_unique()],
+        ),
+        &[Check::success()],
+    );
+
+    context.mollusk.warp_to_slot(11);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+
+    // Same slot: only the two addresses from slot 10 are warmed up.
+    context.process_and_validate_instruction(
+        &addresses_ready_instruction(lookup_table),
+        &[Check::success(), Check::return_data(&2u64.to_le_bytes())],
+    );
+
+    // Next slot: everything is usable.
+    context.mollusk.warp_to_slot(12);
+    context.process_and_validate_instruction(
+        &addresses_ready_instruction(lookup_table),
+        &[Check::success(), Check::return_data(&3u64.to_le_bytes())],
+    );
+}
+
+#[test]
+fn test_addresses_ready_of_deactivated_lookup_table() {
+    let mut table = TestTable::with_addresses(&[Pubkey::new_unique(), Pubkey::new_unique()]);
+    table.advance_clock(1);
+    table.context.process_and_validate_instruction(
+        &addresses_ready_instruction(table.key),
+        &[Check::success(), Check::return_data(&2u64.to_le_bytes())],
+    );
+
+    // Nothing is ready once deactivated, even while the table is cooling
+    // down and could still resolve.
+    table.deactivate();
+    table.context.process_and_validate_instruction(
+        &addresses_ready_instruction(table.key),
+        &[Check::success(), Check::return_data(&0u64.to_le_bytes())],
+    );
+}
+
+#[test]
+fn test_get_age_counts_slots_since_last_extend() {
+    let mut table = TestTable::new();
+
+    // Never extended: `last_extended_slot` is zero, so the age is the
+    // current slot.
+    table.advance_clock(40);
+    table.context.process_and_validate_instruction(
+        &get_age_instruction(table.key),
+        &[Check::success(), Check::return_data(&40u64.to_le_bytes())],
+    );
+
+    table.advance_clock(100);
+    table.extend(&[Pubkey::new_unique()]);
+    assert_eq!(table.meta().last_extended_slot, 100);
+    table.context.process_and_validate_instruction(
+        &get_age_instruction(table.key),
+        &[Check::success(), Check::return_data(&0u64.to_le_bytes())],
+    );
+
+    table.advance_clock(175);
+    table.context.process_and_validate_instruction(
+        &get_age_instruction(table.key),
+        &[Check::success(), Check::return_data(&75u64.to_le_bytes())],
+    );
+}
+
+#[test]
+fn test_get_version_reports_crate_version_and_features() {
+    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    let result = mollusk.process_instruction(&get_version_instruction(), &[]);
+    assert!(result.program_result.is_ok());
+
+    let version = ProgramVersion::from_return_data(&result.return_data).unwrap();
+    let expected: Vec<u64> = env!("CARGO_PKG_VERSION")
+        .split(['-', '+'])
+        .next()
+        .unwrap()
+        .split('.')
+        .map(|part| part.parse().unwrap())
+        .collect();
+    assert_eq!(
+        [
+            version.major as u64,
+            version.minor as u64,
+            version.patch as u64
+        ],
+        expected[..]
+    );
+    assert_eq!(version, ProgramVersion::CURRENT);
+    assert!(version.supports(FEATURE_PAYER_OPTIONAL_EXTEND | FEATURE_TRANSFER_AUTHORITY));
+    assert!(!version.supports(1 << 31));
+
+    assert_eq!(
+        ProgramVersion::from_return_data(&result.return_data[1..]),
+        None
+    );
+}
+
+type LogScenario = fn() -> (Context, Instruction);
+
+#[test]
+fn test_error_logs() {
+    let scenarios: &[(&str, LogScenario, ProgramError, &str)] = &[
+        (
+            "create from a slot that is not recent",
+            || {
+                let (context, _) = create_lookup_table(Pubkey::new_unique(), PAYER, 10_000_000_000);
+                let authority = Pubkey::new_unique();
+                let (lookup_table, bump) = Pubkey::find_program_address(
+                    &[authority.as_ref(), &1_000_000u64.to_le_bytes()],
+                    &PROGRAM_ID,
+                );
+                let instruction =
+                    create_instruction(lookup_table, authority, PAYER, 1_000_000, bump);
+                (context, instruction)
+            },
+            ProgramError::InvalidInstructionData,
+            "1000000 is not a recent slot",
+        ),
+        (
+            "create at an address not derived from the seeds",
+            || {
+                let (context, _) = create_lookup_table(Pubkey::new_unique(), PAYER, 10_000_000_000);
+                let authority = Pubkey::new_unique();
+                let (_, bump) = Pubkey::find_program_address(
+                    &[authority.as_ref(), &0u64.to_le_bytes()],
+                    &PROGRAM_ID,
+                );
+                let instruction =
+                    create_instruction(Pubkey::new_unique(), authority, PAYER, 0, bump);
+                (context, instruction)
+            },
+            ProgramError::InvalidArgument,
+            "Table address must match derived address",
+        ),
+        (
+            "extend with the wrong authority",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                context
+                    .account_store
+                    .borrow_mut()
+                    .insert(AUTHORITY2, Account::default());
+                let instruction =
+                    extend_instruction(lookup_table, AUTHORITY2, PAYER, &[Pubkey::new_unique()]);
+                (context, instruction)
+            },
+            ProgramError::IncorrectAuthority,
+            "Incorrect lookup table authority",
+        ),
+        (
+            "extend a deactivated table",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                context.process_and_validate_instruction(
+                    &deactivate_instruction(lookup_table, AUTHORITY),
+                    &[Check::success()],
+                );
+                let instruction =
+                    extend_instruction(lookup_table, AUTHORITY, PAYER, &[Pubkey::new_unique()]);
+                (context, instruction)
+            },
+            ProgramError::InvalidArgument,
+            "Deactivated tables cannot be extended",
+        ),
+        (
+            "extend a frozen table",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                context.process_and_validate_instruction(
+                    &extend_instruction(lookup_table, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+                    &[Check::success()],
+                );
+                context.process_and_validate_instruction(
+                    &freeze_instruction(lookup_table, AUTHORITY),
+                    &[Check::success()],
+                );
+                let instruction =
+                    extend_instruction(lookup_table, AUTHORITY, PAYER, &[Pubkey::new_unique()]);
+                (context, instruction)
+            },
+            ProgramError::Immutable,
+            "Lookup table is already frozen",
+        ),
+        (
+            "freeze an empty table",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                let instruction = freeze_instruction(lookup_table, AUTHORITY);
+                (context, instruction)
+            },
+            ProgramError::InvalidInstructionData,
+            "Empty lookup tables cannot be frozen",
+        ),
+        (
+            "freeze a deactivating table",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                context.process_and_validate_instruction(
+                    &extend_instruction(lookup_table, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+                    &[Check::success()],
+                );
+                context.process_and_validate_instruction(
+                    &deactivate_instruction(lookup_table, AUTHORITY),
+                    &[Check::success()],
+                );
+                let instruction = freeze_instruction(lookup_table, AUTHORITY);
+                (context, instruction)
+            },
+            ProgramError::InvalidArgument,
+            "Deactivating tables cannot be frozen",
+        ),
+        (
+            "freeze a fully deactivated table",
+            || {
+                let (mut context, lookup_table) =
+                    create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                context.process_and_validate_instruction(
+                    &extend_instruction(lookup_table, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+                    &[Check::success()],
+                );
+                context.process_and_validate_instruction(
+                    &deactivate_instruction(lookup_table, AUTHORITY),
+                    &[Check::success()],
+                );
+                advance_clock(&mut context.mollusk, SLOT_HASHES_MAX_ENTRIES + 1);
+                let instruction = freeze_instruction(lookup_table, AUTHORITY);
+                (context, instruction)
+            },
+            ProgramError::InvalidArgument,
+            "Deactivated tables cannot be frozen",
+        ),
+        (
+            "deactivate twice",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                context.process_and_validate_instruction(
+                    &deactivate_instruction(lookup_table, AUTHORITY),
+                    &[Check::success()],
+                );
+                let instruction = deactivate_instruction(lookup_table, AUTHORITY);
+                (context, instruction)
+            },
+            ProgramError::InvalidArgument,
+            "Lookup table is already deactivated",
+        ),
+        (
+            "close an active table",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                let instruction = close_instruction(lookup_table, AUTHORITY, PAYER);
+                (context, instruction)
+            },
+            ProgramError::InvalidArgument,
+            "Lookup table is not deactivated",
+        ),
+        (
+            "close in the deactivation slot",
+            || {
+                let (context, lookup_table) = create_lookup_table(AUTHORITY, PAYER, 10_000_000_000);
+                context.process_and_validate_instruction(
+                    &deactivate_instruction(lookup_table, AUTHORITY),
+                    &[Check::success()],
+                );
+                let instruction = close_instruction(lookup_table, AUTHORITY, PAYER);
+                (context, instruction)
+            },
+            ProgramError::InvalidArgument,
+            "Table cannot be closed until it's fully deactivated in 513 blocks",
+        ),
+    ];
+
+    for (scenario, setup, error, expected) in scenarios {
+        let (context, instruction) = setup();
+        let logs = logs_of(&context, &instruction, &[Check::err(error.clone())]);
+        assert!(
+            logs.iter().any(|log| log.contains(expected)),
+            "{scenario}: expected a log containing {expected:?}, got {logs:#?}",
+        );
+    }
+}
+
+#[test]
+fn test_freeze_lookup_table_already_frozen() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &freeze_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    let frozen = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .clone();
+
+    // The second freeze runs against the state persisted by the first one.
+    context.process_and_validate_instruction(
+        &freeze_instruction(lookup_table, authority),
+        &[Check::err(ProgramError::Immutable)],
+    );
+    assert_eq!(
+        context.account_store.borrow().get(&lookup_table).unwrap(),
+        &frozen
+    );
+}
+
+#[test]
+fn test_extend_frozen_table_as_zeroed_authority_fails() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &extend_instruction(lookup_table, authority, payer, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &freeze_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    assert_eq!(meta_of(&context, &lookup_table).authority, [0; 32]);
+    let frozen = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .clone();
+
+    // Freezing zeroed the stored authority, so the all-zeros key (the system
+    // program's address) would pass the authority comparison. It can't sign
+    // on a real cluster, but even as a signer it must hit the frozen check
+    // first.
+    let zeroed_authority = Pubkey::new_from_array([0; 32]);
+    context.process_and_validate_instruction(
+        &extend_instruction(
+            lookup_table,
+            zeroed_authority,
+            payer,
+            &[Pubkey::new_unique()],
+        ),
+        &[Check::err(ProgramError::Immutable)],
+    );
+    assert_eq!(
+        context.account_store.borrow().get(&lookup_table).unwrap(),
+        &frozen
+    );
+}
+
+#[test]
+fn test_close_into_lookup_table_with_recipient_guard() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    let other_lookup_table = add_lookup_table(&context, Pubkey::new_unique(), payer);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(wallet, Account::default());
+
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    let mut guarded_close = close_instruction(lookup_table, authority, other_lookup_table);
+    guarded_close
+        .data
+        .push(CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT);
+    context.process_and_validate_instruction(
+        &guarded_close,
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+
+    // A system-owned wallet is always an acceptable recipient.
+    let mut guarded_close = close_instruction(lookup_table, authority, wallet);
+    guarded_close
+        .data
+        .push(CLOSE_REJECT_PROGRAM_OWNED_RECIPIENT);
+    let table_lamports = lamports_of(&context, &lookup_table);
+    context.process_and_validate_instruction(
+        &guarded_close,
+        &[
+            Check::success(),
+            Check::account(&wallet).lamports(table_lamports).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_close_into_lookup_table_without_recipient_guard() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    let other_lookup_table = add_lookup_table(&context, Pubkey::new_unique(), payer);
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    let expected_lamports =
+        lamports_of(&context, &other_lookup_table) + lamports_of(&context, &lookup_table);
+    context.process_and_validate_instruction(
+        &close_instruction(lookup_table, authority, other_lookup_table),
+        &[
+            Check::success(),
+            Check::account(&other_lookup_table)
+                .lamports(expected_lamports)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_close_burning_lamports() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let incinerator = Pubkey::new_from_array(INCINERATOR_ID);
+
+    let (mut context, lookup_table) = create_lookup_table(authority, payer, 10_000_000_000);
+    {
+        let mut accounts = context.account_store.borrow_mut();
+        accounts.insert(wallet, Account::default());
+        accounts.insert(incinerator, Account::default());
+    }
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    // Burning only ever credits the incinerator.
+    let mut burn_into_wallet = close_instruction(lookup_table, authority, wallet);
+    burn_into_wallet.data.push(CLOSE_BURN_LAMPORTS);
+    context.process_and_validate_instruction(
+        &burn_into_wallet,
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+
+    let lamports_before: HashMap<Pubkey, u64> = context
+        .account_store
+        .borrow()
+        .iter()
+        .map(|(key, account)| (*key, account.lamports))
+        .collect();
+    let table_lamports = lamports_before[&lookup_table];
+
+    let mut burn = close_instruction(lookup_table, authority, incinerator);
+    burn.data.push(CLOSE_BURN_LAMPORTS);
+    context.process_and_validate_instruction(
+        &burn,
+        &[
+            Check::success(),
+            Check::account(&lookup_table).lamports(0).space(0).build(),
+            Check::account(&incinerator)
+                .lamports(table_lamports)
+                .build(),
+        ],
+    );
+
+    // No one but the incinerator, which the runtime empties at the end of
+    // the block, received the table's lamports.
+    for (key, account) in context.account_store.borrow().iter() {
+        if *key != lookup_table && *key != incinerator {
+            assert_eq!(account.lamports, lamports_before[key], "{key}");
+        }
+    }
+}
+
+#[test]
+fn test_extend_lookup_table_authority_not_signer() {
+    let table = TestTable::new();
+
+    let mut extend = extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]);
+    extend.accounts[1] = AccountMeta::new_readonly(AUTHORITY, false);
+    table.context.process_and_validate_instruction(
+        &extend,
+        &[Check::err(ProgramError::MissingRequiredSignature)],
+    );
+
+    table.assert_table(&[], false, false);
+}
+
+fn program_rent() -> pinocchio::sysvars::rent::Rent {
+    let rent = Rent::default();
+    pinocchio::sysvars::rent::Rent {
+        lamports_per_byte_year: rent.lamports_per_byte_year,
+        exemption_threshold: rent.exemption_threshold,
+        burn_percent: rent.burn_percent,
+    }
+}
+
+#[test]
+fn test_max_addable_at_rent_boundary() {
+    let rent = Rent::default();
+    let current_count = 10;
+    let table_size = |count: usize| LOOKUP_TABLE_META_SIZE + count * 32;
+    let cost_of = |added: usize| {
+        rent.minimum_balance(table_size(current_count + added))
+            - rent.minimum_balance(table_size(current_count))
+    };
+
+    assert_eq!(max_addable(current_count, 0, &program_rent()), 0);
+    assert_eq!(
+        max_addable(current_count, cost_of(1) - 1, &program_rent()),
+        0
+    );
+    assert_eq!(max_addable(current_count, cost_of(1), &program_rent()), 1);
+    assert_eq!(max_addable(current_count, cost_of(7), &program_rent()), 7);
+    assert_eq!(
+        max_addable(current_count, cost_of(7) - 1, &program_rent()),
+        6
+    );
+}
+
+#[test]
+fn test_max_addable_is_capped_by_capacity() {
+    assert_eq!(max_addable(0, u64::MAX, &program_rent()), 256);
+    assert_eq!(max_addable(250, u64::MAX, &program_rent()), 6);
+    assert_eq!(max_addable(256, u64::MAX, &program_rent()), 0);
+}
+
+#[test]
+fn test_extend_exactly_funded_lookup_table_needs_no_payer_signature() {
+    let rent = Rent::default();
+    let new_table_size = LOOKUP_TABLE_META_SIZE + 32;
+
+    for (table_lamports, expected) in [
+        (rent.minimum_balance(new_table_size), Check::success()),
+        (
+            rent.minimum_balance(new_table_size) - 1,
+            Check::err(ProgramError::MissingRequiredSignature),
+        ),
+    ] {
+        let table = TestTable::new();
+        table
+            .context
+            .account_store
+            .borrow_mut()
+            .get_mut(&table.key)
+            .unwrap()
+            .lamports = table_lamports;
+
+        let mut extend = extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]);
+        extend.accounts[2] = AccountMeta::new(PAYER, false);
+        let payer_lamports = lamports_of(&table.context, &PAYER);
+
+        table
+            .context
+            .process_and_validate_instruction(&extend, &[expected]);
+        assert_eq!(lamports_of(&table.context, &PAYER), payer_lamports);
+    }
+}
+
+#[test]
+fn test_create_pre_funded_lookup_table_charges_payer_nothing() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let rent_exempt_lamports = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE);
+
+    let (context, _) = create_lookup_table(Pubkey::new_unique(), payer, 1_000_000_000);
+    let payer_lamports = lamports_of(&context, &payer);
+
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    {
+        let mut accounts = context.account_store.borrow_mut();
+        accounts.insert(authority, Account::default());
+        accounts.insert(
+            lookup_table,
+            Account {
+                lamports: rent_exempt_lamports,
+                ..Account::default()
+            },
+        );
+    }
+
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, payer, recent_slot, bump),
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .lamports(rent_exempt_lamports)
+                .owner(&PROGRAM_ID)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+            Check::account(&payer).lamports(payer_lamports).build(),
+        ],
+    );
+    assert_table(&context, &lookup_table, &[], false, false);
+}
+
+#[test]
+fn test_create_partially_funded_lookup_table_charges_the_difference() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let rent_exempt_lamports = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE);
+
+    let (context, _) = create_lookup_table(Pubkey::new_unique(), payer, 1_000_000_000);
+    let payer_lamports = lamports_of(&context, &payer);
+
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    {
+        let mut accounts = context.account_store.borrow_mut();
+        accounts.insert(authority, Account::default());
+        accounts.insert(
+            lookup_table,
+            Account {
+                lamports: 1,
+                ..Account::default()
+            },
+        );
+    }
+
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, payer, recent_slot, bump),
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .lamports(rent_exempt_lamports)
+                .build(),
+            Check::account(&payer)
+                .lamports(payer_lamports - (rent_exempt_lamports - 1))
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_create_existing_lookup_table_is_idempotent() {
+    let table = TestTable::with_addresses(&[Pubkey::new_unique(), Pubkey::new_unique()]);
+    let existing = table
+        .context
+        .account_store
+        .borrow()
+        .get(&table.key)
+        .unwrap()
+        .clone();
+
+    let recent_slot: u64 = 0;
+    let (_, bump) = Pubkey::find_program_address(
+        &[AUTHORITY.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    let logs = logs_of(
+        &table.context,
+        &create_instruction(table.key, AUTHORITY, PAYER, recent_slot, bump),
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        table
+            .context
+            .account_store
+            .borrow()
+            .get(&table.key)
+            .unwrap(),
+        &existing
+    );
+    // Nothing was created, so nothing is reported as created.
+    assert!(!logs.iter().any(|log| log.contains("Created lookup table")));
+}
+
+#[test]
+fn test_create_reinitializes_empty_program_owned_lookup_table() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, _) = create_lookup_table(Pubkey::new_unique(), payer, 10_000_000_000);
+
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    {
+        let mut accounts = context.account_store.borrow_mut();
+        accounts.insert(authority, Account::default());
+        accounts.insert(
+            lookup_table,
+            Account {
+                owner: PROGRAM_ID,
+                ..Account::default()
+            },
+        );
+    }
+
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, payer, recent_slot, bump),
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .lamports(Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE))
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+        ],
+    );
+    assert_table(&context, &lookup_table, &[], false, false);
+    assert_eq!(
+        meta_of(&context, &lookup_table).authority,
+        authority.to_bytes()
+    );
+}
+
+#[test]
+fn test_deactivate_then_reactivate_then_deactivate() {
+    let mut table = TestTable::new();
+    table.extend(&[Pubkey::new_unique()]);
+
+    table.advance_clock(5);
+    table.deactivate();
+    assert_eq!(table.meta().deactivation_slot, 5);
+
+    table.context.process_and_validate_instruction(
+        &reactivate_instruction(table.key, AUTHORITY),
+        &[Check::success()],
+    );
+    assert_eq!(table.meta().deactivation_slot, u64::MAX);
+
+    table.advance_clock(6);
+    table.extend(&[Pubkey::new_unique()]);
+
+    table.advance_clock(9);
+    table.deactivate();
+    assert_eq!(table.meta().deactivation_slot, 9);
+    assert_eq!(table.addresses().len(), 2);
+}
+
+#[test]
+fn test_reactivate_active_lookup_table_fails() {
+    let table = TestTable::new();
+
+    table.context.process_and_validate_instruction(
+        &reactivate_instruction(table.key, AUTHORITY),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+}
+
+#[test]
+fn test_extend_logs_the_funding_payer() {
+    let table = TestTable::new();
+
+    let logs = logs_of(
+        &table.context,
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Extended lookup table to 1 addresses, funded by payer")));
+    assert!(logs.iter().any(|log| log.contains(&PAYER.to_string())));
+
+    // Without a top-up the payer is not recorded.
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .get_mut(&table.key)
+        .unwrap()
+        .lamports = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE + 2 * 32);
+    let logs = logs_of(
+        &table.context,
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    assert!(!logs.iter().any(|log| log.contains("funded by payer")));
+    assert!(!logs.iter().any(|log| log.contains(&PAYER.to_string())));
+}
+
+#[test]
+fn test_unauthorized_extend_fails_cheaply() {
+    let table = TestTable::new();
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .insert(AUTHORITY2, Account::default());
+
+    let authorized = table.context.process_and_validate_instruction(
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    let unauthorized = table.context.process_and_validate_instruction(
+        &extend_instruction(table.key, AUTHORITY2, PAYER, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+
+    // The authority is rejected before any sysvar read, resize or CPI, so
+    // the failure costs little more than the two log lines.
+    assert!(unauthorized.compute_units_consumed < 1_000);
+    assert!(unauthorized.compute_units_consumed * 2 < authorized.compute_units_consumed);
+}
+
+#[test]
+fn test_lookup_table_cannot_be_its_own_authority() {
+    let table = TestTable::with_addresses(&[Pubkey::new_unique()]);
+
+    // Handing the table to itself would leave it to whoever can sign for
+    // the PDA, which is only this program.
+    table.context.process_and_validate_instruction(
+        &transfer_authority_instruction(table.key, AUTHORITY, table.key),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+
+    // A meta naming the table as authority can't be produced by the program,
+    // but extend still refuses a table signing for itself.
+    let addresses = table.addresses();
+    table.context.account_store.borrow_mut().insert(
+        table.key,
+        make_lookup_table_account(Some(table.key), &addresses, u64::MAX),
+    );
+    let mut extend = extend_instruction(table.key, table.key, PAYER, &[Pubkey::new_unique()]);
+    extend.accounts[1] = AccountMeta::new(table.key, true);
+    extend.accounts[0].is_signer = true;
+    table
+        .context
+        .process_and_validate_instruction(&extend, &[Check::err(ProgramError::InvalidArgument)]);
+}
+
+#[cfg(feature = "custom-program-id")]
+#[test]
+fn test_custom_program_id_derivation_and_create_agree() {
+    let expected = option_env!("LOOKUP_TABLE_PROGRAM_ID").map_or(
+        Pubkey::from_str_const("AddressLookupTab1e1111111111111111111111111"),
+        |id| Pubkey::from_str_const(id),
+    );
+    assert_eq!(PROGRAM_ID, expected);
+    assert!(p_address_lookup_table::check_id(&expected.to_bytes()));
+
+    // The fixture program runs under whatever id mollusk loads it with, so
+    // creating through it checks the derivation against the overridden id.
+    let table = TestTable::new();
+    let (_, bump) =
+        Pubkey::find_program_address(&[AUTHORITY.as_ref(), &0u64.to_le_bytes()], &PROGRAM_ID);
+    let derived = derive_lookup_table_address(
+        &AUTHORITY.to_bytes(),
+        0,
+        bump,
+        &p_address_lookup_table::LOOKUP_TABLE_PROGRAM_ID,
+    );
+    assert_eq!(Pubkey::new_from_array(derived), table.key);
+}
+
+#[test]
+fn test_format_log() {
+    use p_address_lookup_table::log::{format_log, ProgramLog};
+
+    let table = Pubkey::new_unique().to_bytes();
+    let cases = [
+        (
+            ProgramLog::Create {
+                authority: AUTHORITY.to_bytes(),
+                slot: u64::MAX,
+            },
+            "Created lookup table for slot 18446744073709551615 with authority",
+        ),
+        (ProgramLog::Freeze { table }, "Froze lookup table"),
+        (
+            ProgramLog::Extend {
+                table,
+                new_count: 256,
+                payer: None,
+            },
+            "Extended lookup table to 256 addresses",
+        ),
+        (
+            ProgramLog::Extend {
+                table,
+                new_count: 3,
+                payer: Some(table),
+            },
+            "Extended lookup table to 3 addresses, funded by payer",
+        ),
+        (
+            ProgramLog::Deactivate { table, slot: 7 },
+            "Deactivated lookup table at slot 7",
+        ),
+        (
+            ProgramLog::Close {
+                table,
+                recipient: table,
+            },
+            "Closed lookup table",
+        ),
+    ];
+    for (event, expected) in cases {
+        assert_eq!(&*format_log(&event), expected.as_bytes());
+    }
+}
+
+#[test]
+fn test_success_logs() {
+    let mut table = TestTable::new();
+    let table_key = table.key.to_string();
+
+    let logs = logs_of(
+        &table.context,
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Extended lookup table to 1 addresses")));
+    assert!(logs.iter().any(|log| log.contains(&table_key)));
+
+    let logs = logs_of(
+        &table.context,
+        &deactivate_instruction(table.key, AUTHORITY),
+        &[Check::success()],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Deactivated lookup table at slot 0")));
+    assert!(logs.iter().any(|log| log.contains(&table_key)));
+
+    let recipient = Pubkey::new_unique();
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .insert(recipient, Account::default());
+    table.advance_clock(SLOT_HASHES_MAX_ENTRIES + 1);
+    let logs = logs_of(
+        &table.context,
+        &close_instruction(table.key, AUTHORITY, recipient),
+        &[Check::success()],
+    );
+    assert!(logs.iter().any(|log| log.contains("Closed lookup table")));
+    assert!(logs.iter().any(|log| log.contains(&recipient.to_string())));
+}
+
+#[test]
+fn test_extend_full_but_one_table_in_new_slot() {
+    let addresses: Vec<Pubkey> = (0..LOOKUP_TABLE_MAX_ADDRESSES - 1)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    let mut table = TestTable::with_addresses(&addresses);
+    assert_eq!(table.meta().last_extended_slot_start_index, 0);
+
+    table.advance_clock(1);
+    table.extend(&[Pubkey::new_unique()]);
+
+    let meta = table.meta();
+    assert_eq!(meta.last_extended_slot, 1);
+    assert_eq!(meta.last_extended_slot_start_index, 255);
+    assert_eq!(table.addresses().len(), LOOKUP_TABLE_MAX_ADDRESSES);
+}
+
+#[test]
+fn test_extend_lookup_table_where_new_count_equals_exactly_max() {
+    let addresses: Vec<Pubkey> = (0..LOOKUP_TABLE_MAX_ADDRESSES - 1)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    let table = TestTable::with_addresses(&addresses);
+
+    // One address short of full, two more would overflow: the
+    // `new_table_addresses_len > LOOKUP_TABLE_MAX_ADDRESSES` check.
+    let logs = logs_of(
+        &table.context,
+        &extend_instruction(
+            table.key,
+            AUTHORITY,
+            PAYER,
+            &[Pubkey::new_unique(), Pubkey::new_unique()],
+        ),
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("would exceed max capacity of 256")));
+
+    // Exactly reaching the cap is allowed.
+    table.extend(&[Pubkey::new_unique()]);
+    assert_eq!(table.addresses().len(), LOOKUP_TABLE_MAX_ADDRESSES);
+
+    // A full table refuses even one more: the
+    // `old_table_addresses_len >= LOOKUP_TABLE_MAX_ADDRESSES` check.
+    let logs = logs_of(
+        &table.context,
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Lookup table is full and cannot contain more addresses")));
+    assert_eq!(table.addresses().len(), LOOKUP_TABLE_MAX_ADDRESSES);
+}
+
+#[test]
+fn test_created_table_address_rederives_from_bump() {
+    let (context, _) = create_lookup_table(Pubkey::new_unique(), PAYER, 10_000_000_000);
+
+    let authority = Pubkey::new_unique();
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    context
+        .account_store
+        .borrow_mut()
+        .insert(lookup_table, Account::default());
+
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, PAYER, recent_slot, bump),
+        &[
+            Check::success(),
+            Check::account(&lookup_table).owner(&PROGRAM_ID).build(),
+        ],
+    );
+
+    // The table does not store its bump, so (authority, slot, bump) is all a
+    // client has to find it again.
+    let rederived = Pubkey::create_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes(), &[bump]],
+        &PROGRAM_ID,
+    )
+    .unwrap();
+    assert_eq!(rederived, lookup_table);
+    assert_eq!(
+        meta_of(&context, &rederived).authority,
+        authority.to_bytes()
+    );
+}
+
+#[test]
+fn test_deactivate_with_reason() {
+    let table = TestTable::new();
+    table.context.process_and_validate_instruction(
+        &deactivate_with_reason_instruction(table.key, AUTHORITY, 2),
+        &[Check::success()],
+    );
+
+    let meta = table.meta();
+    assert_eq!(meta.deactivation_slot, 0);
+    assert_eq!(meta.deactivation_reason, 2);
+
+    // Reactivating clears the reason, and the plain 4-byte payload defaults
+    // to zero.
+    table.context.process_and_validate_instruction(
+        &reactivate_instruction(table.key, AUTHORITY),
+        &[Check::success()],
+    );
+    assert_eq!(table.meta().deactivation_reason, 0);
+
+    table.deactivate();
+    assert_eq!(table.meta().deactivation_reason, 0);
+}
+
+#[test]
+fn test_serialize_new_lookup_table_over_dirty_buffer() {
+    let authority = Pubkey::new_unique().to_bytes();
+
+    // A frozen, deactivated table with two addresses left in the buffer,
+    // starting one byte into the allocation so the meta is not aligned.
+    let mut buffer = vec![0xab; 1 + LOOKUP_TABLE_META_SIZE + 2 * 32];
+    let data = &mut buffer[1..];
+    data[AUTHORITY_TAG_OFFSET] = 0;
+    serialize_new_lookup_table(data, &authority).unwrap();
+
+    let mut expected = Vec::with_capacity(LOOKUP_TABLE_META_SIZE);
+    expected.extend_from_slice(&LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes());
+    expected.extend_from_slice(&u64::MAX.to_le_bytes());
+    expected.extend_from_slice(&0u64.to_le_bytes());
+    expected.push(0);
+    expected.push(1);
+    expected.extend_from_slice(&authority);
+    expected.extend_from_slice(&[0, 0]);
+    assert_eq!(data[..LOOKUP_TABLE_META_SIZE], expected[..]);
+    assert!(data[LOOKUP_TABLE_META_SIZE..].iter().all(|byte| *byte == 0));
+    assert_eq!(buffer[0], 0xab);
+    let data = &buffer[1..];
+
+    let meta = LookupTable::from_bytes(data).unwrap().meta();
+    assert_eq!(meta.deactivation_slot, u64::MAX);
+    assert_eq!(meta.last_extended_slot, 0);
+    assert_eq!(meta.last_extended_slot_start_index, 0);
+    assert_eq!(meta.authority, authority);
+    assert!(!meta.is_frozen());
+    assert_eq!(meta.version, 0);
+    assert_eq!(meta.deactivation_reason, 0);
+
+    let mut short = vec![0xab; LOOKUP_TABLE_META_SIZE - 1];
+    assert_eq!(
+        serialize_new_lookup_table(&mut short, &authority),
+        Err(StateError::InvalidAccountData)
+    );
+    assert!(short.iter().all(|byte| *byte == 0xab));
+}
+
+#[test]
+fn test_lookup_table_view_lookup() {
+    let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let table = TestTable::with_addresses(&addresses);
+    let account = table.context.account_store.borrow()[&table.key].clone();
+    let view = lookup_table_view(&account);
+
+    // Addresses appended in slot 0 are only active from slot 1.
+    let mut resolved = [[0u8; 32]; 2];
+    assert_eq!(view.get_active_addresses_len(0), 0);
+    assert_eq!(
+        view.lookup(0, &[0, 2], &mut resolved),
+        Err(StateError::InvalidArgument)
+    );
+
+    assert_eq!(view.get_active_addresses_len(1), 3);
+    view.lookup(1, &[2, 0], &mut resolved).unwrap();
+    assert_eq!(resolved, [addresses[2].to_bytes(), addresses[0].to_bytes()]);
+    assert!(view.lookup(1, &[3], &mut resolved[..1]).is_err());
+    assert!(view.lookup(1, &[0], &mut resolved).is_err());
+}
+
+#[test]
+fn test_lookup_table_view_with_start_index_past_addresses() {
+    let addresses: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+    let mut data = vec![0; LOOKUP_TABLE_META_SIZE];
+    serialize_new_lookup_table(&mut data, &AUTHORITY.to_bytes()).unwrap();
+    data[LAST_EXTENDED_SLOT_OFFSET..LAST_EXTENDED_SLOT_OFFSET + 8]
+        .copy_from_slice(&5u64.to_le_bytes());
+    data[LAST_EXTENDED_SLOT_START_INDEX_OFFSET] = 4;
+    data.extend(addresses.iter().flat_map(|address| address.to_bytes()));
+    let view = LookupTable::from_bytes(&data).unwrap();
+
+    // Only data that was not written by the program can claim more active
+    // addresses than it holds; the reader caps rather than panics.
+    assert_eq!(view.get_active_addresses_len(5), 2);
+    let mut resolved = [[0u8; 32]; 1];
+    view.lookup(5, &[1], &mut resolved).unwrap();
+    assert_eq!(resolved, [addresses[1].to_bytes()]);
+    assert_eq!(
+        view.lookup(5, &[3], &mut resolved),
+        Err(StateError::InvalidArgument)
+    );
+}
+
+#[test]
+fn test_extend_records_start_index_of_new_addresses() {
+    let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let mut table = TestTable::with_addresses(&addresses);
+
+    table.advance_clock(5);
+    let result = table.context.process_and_validate_instruction(
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]),
+        &[Check::success()],
+    );
+
+    let data = &result.get_account(&table.key).unwrap().data;
+    assert_eq!(
+        data[LAST_EXTENDED_SLOT_OFFSET..LAST_EXTENDED_SLOT_OFFSET + 8],
+        5u64.to_le_bytes()
+    );
+    assert_eq!(
+        data[LAST_EXTENDED_SLOT_START_INDEX_OFFSET],
+        addresses.len() as u8
+    );
+}
+
+#[test]
+fn test_create_with_same_authority_and_payer() {
+    // Nothing requires the authority and payer to differ: the shared
+    // account is writable and signs for both roles, pays the rent, and ends
+    // up as the authority.
+    let authority = Pubkey::new_unique();
+    let starting_lamports = 10_000_000_000;
+    let (context, lookup_table) = create_lookup_table(authority, authority, starting_lamports);
+
+    let rent = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE);
+    assert_eq!(lamports_of(&context, &authority), starting_lamports - rent);
+    assert_eq!(lamports_of(&context, &lookup_table), rent);
+    assert_eq!(
+        meta_of(&context, &lookup_table).authority,
+        authority.to_bytes()
+    );
+}
+
+#[test]
+fn test_unknown_discriminator_fails_with_distinct_code() {
+    let table = TestTable::new();
+
+    let mut instruction = freeze_instruction(table.key, AUTHORITY);
+    instruction.data = 99u32.to_le_bytes().to_vec();
+    table.context.process_and_validate_instruction(
+        &instruction,
+        &[Check::err(ProgramError::Custom(
+            LookupTableError::UnknownInstruction as u32,
+        ))],
+    );
+
+    // A known instruction with a malformed payload is still reported as bad
+    // instruction data.
+    let mut instruction = extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]);
+    instruction.data.pop();
+    table.context.process_and_validate_instruction(
+        &instruction,
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+}
+
+#[test]
+fn test_empty_extend_of_full_table_reports_full() {
+    let addresses: Vec<Pubkey> = (0..LOOKUP_TABLE_MAX_ADDRESSES)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    let table = TestTable::with_addresses(&addresses);
+
+    let logs = logs_of(
+        &table.context,
+        &extend_instruction(table.key, AUTHORITY, PAYER, &[]),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Lookup table is full and cannot contain more addresses")));
+    assert!(!logs
+        .iter()
+        .any(|log| log.contains("Must extend with at least one address")));
+}
+
+/// Builds an instruction the way the Anchor CPI wrappers do, from the
+/// shared account layout, without depending on Anchor.
+fn instruction_from_layout<const N: usize>(
+    keys: [Pubkey; N],
+    accesses: &[AccountAccess; N],
+    data: Vec<u8>,
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: keys
+            .iter()
+            .zip(accesses)
+            .map(|(key, access)| AccountMeta {
+                pubkey: *key,
+                is_signer: access.is_signer,
+                is_writable: access.is_writable,
+            })
+            .collect(),
+        data,
+    }
+}
+
+#[test]
+fn test_cpi_account_layouts_match_processor() {
+    let (context, _) = create_lookup_table(Pubkey::new_unique(), PAYER, 10_000_000_000);
+
+    let authority = Pubkey::new_unique();
+    let (lookup_table, bump) =
+        Pubkey::find_program_address(&[authority.as_ref(), &0u64.to_le_bytes()], &PROGRAM_ID);
+    {
+        let mut accounts = context.account_store.borrow_mut();
+        accounts.insert(lookup_table, Account::default());
+        accounts.insert(authority, Account::default());
+    }
+    let keys = [
+        lookup_table,
+        authority,
+        PAYER,
+        program::keyed_account_for_system_program().0,
+    ];
+
+    context.process_and_validate_instruction(
+        &instruction_from_layout(
+            keys,
+            &CREATE_LOOKUP_TABLE_ACCOUNTS,
+            create_lookup_table_data(0, bump),
+        ),
+        &[Check::success()],
+    );
+
+    let address = Pubkey::new_unique();
+    let mut builder = ExtendInstructionBuilder::default();
+    builder.push(address.to_bytes()).unwrap();
+    context.process_and_validate_instruction(
+        &instruction_from_layout(keys, &EXTEND_LOOKUP_TABLE_ACCOUNTS, builder.build()),
+        &[Check::success()],
+    );
+    assert_eq!(addresses_of(&context, &lookup_table), [address]);
+}
+
+#[test]
+fn test_plan_table_creation_builds_the_planned_table() {
+    let (context, _) = create_lookup_table(Pubkey::new_unique(), PAYER, 10_000_000_000);
+    let authority = Pubkey::new_unique();
+    let recent_slot: u64 = 0;
+    let (_, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    context
+        .account_store
+        .borrow_mut()
+        .insert(authority, Account::default());
+
+    let addresses: Vec<Pubkey> = (0..2 * MAX_ADDRESSES_PER_EXTEND + 6)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    let address_bytes: Vec<[u8; 32]> = addresses.iter().map(Pubkey::to_bytes).collect();
+    let plan = plan_table_creation(
+        &address_bytes,
+        &authority.to_bytes(),
+        &PAYER.to_bytes(),
+        recent_slot,
+        bump,
+        &program_rent(),
+    )
+    .unwrap();
+
+    // Create, then three extends, the first sharing the create transaction.
+    assert_eq!(plan.instructions.len(), 4);
+    assert_eq!(plan.transactions_needed, 3);
+
+    let table = Pubkey::new_from_array(plan.table_address);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(table, Account::default());
+    let payer_lamports = lamports_of(&context, &PAYER);
+
+    for planned in &plan.instructions {
+        let instruction = Instruction {
+            program_id: Pubkey::new_from_array(planned.program_id),
+            accounts: planned
+                .accounts
+                .iter()
+                .map(|(key, access)| AccountMeta {
+                    pubkey: Pubkey::new_from_array(*key),
+                    is_signer: access.is_signer,
+                    is_writable: access.is_writable,
+                })
+                .collect(),
+            data: planned.data.clone(),
+        };
+        context.process_and_validate_instruction(&instruction, &[Check::success()]);
+    }
+
+    assert_eq!(addresses_of(&context, &table), addresses);
+    assert_eq!(
+        payer_lamports - lamports_of(&context, &PAYER),
+        plan.estimated_rent_lamports
+    );
+    assert_eq!(lamports_of(&context, &table), plan.estimated_rent_lamports);
+}
+
+#[test]
+fn test_plan_table_creation_edge_sizes() {
+    let plan = |len: usize| {
+        plan_table_creation(
+            &vec![[7; 32]; len],
+            &AUTHORITY.to_bytes(),
+            &PAYER.to_bytes(),
+            0,
+            255,
+            &program_rent(),
+        )
+    };
+
+    // Only the create, which still needs a transaction.
+    let empty = plan(0).unwrap();
+    assert_eq!(empty.instructions.len(), 1);
+    assert_eq!(empty.transactions_needed, 1);
+    assert_eq!(
+        empty.estimated_rent_lamports,
+        Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE)
+    );
+
+    assert_eq!(
+        plan(MAX_ADDRESSES_PER_EXTEND).unwrap().transactions_needed,
+        1
+    );
+    assert_eq!(
+        plan(MAX_ADDRESSES_PER_EXTEND + 1)
+            .unwrap()
+            .transactions_needed,
+        2
+    );
+    assert_eq!(
+        plan(LOOKUP_TABLE_MAX_ADDRESSES).unwrap().instructions.len(),
+        11
+    );
+    assert_eq!(
+        plan(LOOKUP_TABLE_MAX_ADDRESSES + 1).unwrap_err(),
+        pinocchio::program_error::ProgramError::InvalidArgument
+    );
+}
+
+#[test]
+fn test_freeze_lookup_table_not_owned_by_program_fails() {
+    let table = TestTable::with_addresses(&[Pubkey::new_unique()]);
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .get_mut(&table.key)
+        .unwrap()
+        .owner = program::keyed_account_for_system_program().0;
+
+    // The table data is intact and the authority signs, so only the owner
+    // check can reject this.
+    table.context.process_and_validate_instruction(
+        &freeze_instruction(table.key, AUTHORITY),
+        &[Check::err(ProgramError::InvalidAccountOwner)],
+    );
+}
+
+/// Binds the table to an allowlist holding `addresses` in sorted order,
+/// appended in batches that stay under the per-instruction growth limit.
+fn bind_allowlist(table: &TestTable, addresses: &[Pubkey]) -> Pubkey {
+    let mut addresses = addresses.to_vec();
+    addresses.sort();
+
+    let mut batches: Vec<&[Pubkey]> = addresses.chunks(256).collect();
+    if batches.is_empty() {
+        batches.push(&[]);
+    }
+    for batch in batches {
+        table.context.process_and_validate_instruction(
+            &extend_allowlist_instruction(table.key, AUTHORITY, PAYER, batch),
+            &[Check::success()],
+        );
+    }
+
+    let allowlist = allowlist_address(table.key).0;
+    assert_eq!(table.meta().authority, allowlist.to_bytes());
+    allowlist
+}
+
+#[test]
+fn test_extend_with_allowlist() {
+    let table = TestTable::new();
+    let allowed: Vec<Pubkey> = (0..64).map(|_| Pubkey::new_unique()).collect();
+    let allowlist = bind_allowlist(&table, &allowed);
+
+    table.context.process_and_validate_instruction(
+        &extend_with_allowlist_instruction(table.key, AUTHORITY, allowlist, PAYER, &allowed[..32]),
+        &[Check::success()],
+    );
+    assert_eq!(table.addresses(), allowed[..32]);
+
+    // One stranger in the batch rejects the whole batch.
+    let mut batch = allowed[32..40].to_vec();
+    batch.push(Pubkey::new_unique());
+    table.context.process_and_validate_instruction(
+        &extend_with_allowlist_instruction(table.key, AUTHORITY, allowlist, PAYER, &batch),
+        &[Check::err(ProgramError::Custom(
+            LookupTableError::AddressNotAllowed as u32,
+        ))],
+    );
+    assert_eq!(table.addresses().len(), 32);
+}
+
+#[test]
+fn test_extend_with_empty_allowlist_rejects_everything() {
+    let table = TestTable::new();
+    let allowlist = bind_allowlist(&table, &[]);
+
+    table.context.process_and_validate_instruction(
+        &extend_with_allowlist_instruction(
+            table.key,
+            AUTHORITY,
+            allowlist,
+            PAYER,
+            &[Pubkey::new_unique()],
+        ),
+        &[Check::err(ProgramError::Custom(
+            LookupTableError::AddressNotAllowed as u32,
+        ))],
+    );
+}
+
+#[test]
+fn test_extend_full_batch_with_large_allowlist_stays_cheap() {
+    let table = TestTable::new();
+    let allowed: Vec<Pubkey> = (0..4_096).map(|_| Pubkey::new_unique()).collect();
+    let allowlist = bind_allowlist(&table, &allowed);
+
+    let result = table.context.process_and_validate_instruction(
+        &extend_with_allowlist_instruction(
+            table.key,
+            AUTHORITY,
+            allowlist,
+            PAYER,
+            &allowed[..LOOKUP_TABLE_MAX_ADDRESSES],
+        ),
+        &[Check::success()],
+    );
+    assert!(
+        result.compute_units_consumed < 200_000,
+        "extend used {} CU",
+        result.compute_units_consumed
+    );
+}
+
+#[test]
+fn test_bound_allowlist_cannot_be_bypassed() {
+    let table = TestTable::new();
+    let allowed = [Pubkey::new_unique()];
+    bind_allowlist(&table, &allowed);
+
+    // The allowlist is now the authority, so the plain extends and the other
+    // authority instructions are refused, even for allowed addresses.
+    let stranger = [Pubkey::new_unique()];
+    for instruction in [
+        extend_instruction(table.key, AUTHORITY, PAYER, &stranger),
+        extend_checked_instruction(table.key, AUTHORITY, PAYER, table.meta().version, &stranger),
+        extend_instruction(table.key, AUTHORITY, PAYER, &allowed),
+        freeze_instruction(table.key, AUTHORITY),
+        deactivate_instruction(table.key, AUTHORITY),
+        transfer_authority_instruction(table.key, AUTHORITY, AUTHORITY2),
+    ] {
+        table.context.process_and_validate_instruction(
+            &instruction,
+            &[Check::err(ProgramError::IncorrectAuthority)],
+        );
+    }
+    assert!(table.addresses().is_empty());
+}
+
+#[test]
+fn test_allowlist_is_not_accepted_as_a_table() {
+    let table = TestTable::new();
+    let allowlist = bind_allowlist(&table, &[Pubkey::new_unique()]);
+
+    // The allowlist is owned by this program and large enough to hold a
+    // meta, so only the discriminator tells it apart from a table.
+    for instruction in [
+        top_up_rent_instruction(allowlist, PAYER),
+        rent_status_instruction(allowlist),
+        addresses_ready_instruction(allowlist),
+        get_age_instruction(allowlist),
+        collect_addresses_instruction(&[table.key, allowlist]),
+    ] {
+        table.context.process_and_validate_instruction(
+            &instruction,
+            &[Check::err(ProgramError::InvalidAccountData)],
+        );
+    }
+}
+
+#[test]
+fn test_extend_with_unbound_allowlist_fails() {
+    let table = TestTable::new();
+    let allowed = [Pubkey::new_unique()];
+
+    // An account holding the right addresses but not created by this
+    // program for this table is refused.
+    let forged = Pubkey::new_unique();
+    let mut data = vec![0; ALLOWLIST_HEADER_SIZE];
+    data[..32].copy_from_slice(AUTHORITY.as_ref());
+    data.extend_from_slice(allowed[0].as_ref());
+    table.context.account_store.borrow_mut().insert(
+        forged,
+        Account {
+            lamports: 1,
+            data,
+            owner: PROGRAM_ID,
+            ..Account::default()
+        },
+    );
+    table.context.process_and_validate_instruction(
+        &extend_with_allowlist_instruction(table.key, AUTHORITY, forged, PAYER, &allowed),
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+
+    // Nor is the bound allowlist once another program owns it.
+    let allowlist = bind_allowlist(&table, &allowed);
+    table
+        .context
+        .account_store
+        .borrow_mut()
+        .get_mut(&allowlist)
+        .unwrap()
+        .owner = Pubkey::new_unique();
+    table.context.process_and_validate_instruction(
+        &extend_with_allowlist_instruction(table.key, AUTHORITY, allowlist, PAYER, &allowed),
+        &[Check::err(ProgramError::InvalidAccountOwner)],
+    );
+    assert!(table.addresses().is_empty());
+}
+
+#[test]
+fn test_extend_allowlist_requires_sorted_addresses() {
+    let table = TestTable::new();
+    let mut allowed: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    allowed.sort();
+    bind_allowlist(&table, &allowed[1..2]);
+
+    for batch in [[allowed[0]], [allowed[1]]] {
+        table.context.process_and_validate_instruction(
+            &extend_allowlist_instruction(table.key, AUTHORITY, PAYER, &batch),
+            &[Check::err(ProgramError::InvalidInstructionData)],
+        );
+    }
+    table.context.process_and_validate_instruction(
+        &extend_allowlist_instruction(table.key, AUTHORITY, PAYER, &allowed[2..]),
+        &[Check::success()],
+    );
+
+    // Only the controller can append.
+    table.context.process_and_validate_instruction(
+        &extend_allowlist_instruction(table.key, AUTHORITY2, PAYER, &[Pubkey::new_unique()]),
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+}
+
+#[test]
+fn test_clear_allowlist_restores_authority() {
+    let table = TestTable::new();
+    let allowlist = bind_allowlist(&table, &[Pubkey::new_unique()]);
+    let allowlist_lamports = lamports_of(&table.context, &allowlist);
+    let payer_lamports = lamports_of(&table.context, &PAYER);
+
+    table.context.process_and_validate_instruction(
+        &clear_allowlist_instruction(table.key, AUTHORITY2, PAYER),
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+    table.context.process_and_validate_instruction(
+        &clear_allowlist_instruction(table.key, AUTHORITY, PAYER),
+        &[Check::success()],
+    );
+
+    assert_eq!(table.meta().authority, AUTHORITY.to_bytes());
+    assert_eq!(lamports_of(&table.context, &allowlist), 0);
+    assert_eq!(
+        lamports_of(&table.context, &PAYER),
+        payer_lamports + allowlist_lamports
+    );
+
+    let stranger = [Pubkey::new_unique()];
+    table.extend(&stranger);
+    assert_eq!(table.addresses(), stranger);
+}
+
+#[test]
+fn test_last_extended_slot_addresses() {
+    let first: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let mut table = TestTable::with_addresses(&first);
+
+    table.advance_clock(1);
+    let second: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+    table.extend(&second);
+    // Same slot, so these join the addresses of the last extension.
+    let third = [Pubkey::new_unique()];
+    table.extend(&third);
+
+    let account = table.context.account_store.borrow()[&table.key].clone();
+    let expected: Vec<u8> = second
+        .iter()
+        .chain(&third)
+        .flat_map(|address| address.to_bytes())
+        .collect();
+    assert_eq!(
+        table.meta().last_extended_slot_addresses(&account.data),
+        Ok(&expected[..])
+    );
+    assert!(table
+        .meta()
+        .last_extended_slot_addresses(&account.data[..LOOKUP_TABLE_META_SIZE])
+        .is_err());
+}
+
+#[test]
+fn test_exact_rent_exempt_funding() {
+    // Default rent: 3,480 lamports per byte-year, exempt after two years,
+    // plus 128 bytes of account overhead.
+    let exempt = |addresses: u64| (128 + 56 + 32 * addresses) * 3_480 * 2;
+    assert_eq!(exempt(0), 1_280_640);
+
+    let payer_lamports = 10_000_000_000;
+    let table = TestTable::create(AUTHORITY, PAYER, payer_lamports);
+    assert_eq!(table.lamports(), 1_280_640);
+    assert_eq!(
+        lamports_of(&table.context, &PAYER),
+        payer_lamports - 1_280_640
+    );
+
+    let mut count = 0;
+    for (added, total_exempt) in [(1, 1_503_360), (9, 3_507_840), (246, 58_296_960)] {
+        let payer_before = lamports_of(&table.context, &PAYER);
+        let table_before = table.lamports();
+
+        let addresses: Vec<Pubkey> = (0..added).map(|_| Pubkey::new_unique()).collect();
+        table.extend(&addresses);
+        count += added;
+
+        // Exactly rent exempt, and exactly the difference was charged.
+        assert_eq!(exempt(count), total_exempt);
+        assert_eq!(table.lamports(), total_exempt);
+        assert_eq!(
+            payer_before - lamports_of(&table.context, &PAYER),
+            total_exempt - table_before
+        );
+    }
+    assert_eq!(count, LOOKUP_TABLE_MAX_ADDRESSES as u64);
+}
+
+#[test]
+fn test_rent_free_cluster_still_funds_one_lamport() {
+    // With no rent the `max(1)` keeps the table from being created with zero
+    // lamports, which the runtime would treat as a missing account.
+    let mut mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    mollusk.sysvars.rent = Rent {
+        lamports_per_byte_year: 0,
+        ..Rent::default()
+    };
+    let (system_program, system_account) = program::keyed_account_for_system_program();
+    let (slot_key, slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+    let context = mollusk.with_context(HashMap::from([
+        (
+            PAYER,
+            Account {
+                lamports: 10,
+                ..Account::default()
+            },
+        ),
+        (slot_key, slot_account),
+        (system_program, system_account),
+    ]));
+
+    let lookup_table = add_lookup_table(&context, AUTHORITY, PAYER);
+    assert_eq!(lamports_of(&context, &lookup_table), 1);
+    assert_eq!(lamports_of(&context, &PAYER), 9);
+}
+
+#[test]
+fn test_extend_lookup_table_then_read_via_lookup_table_view() {
+    let table = TestTable::new();
+    let new_addresses: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    table.extend(&new_addresses);
+
+    let store = table.context.account_store.borrow();
+    let data = &store.get(&table.key).unwrap().data;
+    let view = LookupTable::from_bytes(data).unwrap();
+
+    assert_eq!(view.iter_addresses().len(), new_addresses.len());
+    for (read, written) in view.iter_addresses().zip(&new_addresses) {
+        assert_eq!(read, &written.to_bytes());
+    }
+}
+
+#[test]
+fn test_close_from_layout_with_authority_as_recipient() {
+    let authority = Pubkey::new_unique();
+    let (mut context, lookup_table) = create_lookup_table(authority, PAYER, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    // Read-only in both roles, the authority can't take the lamports.
+    let mut close = instruction_from_layout(
+        [lookup_table, authority, authority],
+        &CLOSE_LOOKUP_TABLE_ACCOUNTS,
+        CLOSE_LOOKUP_TABLE.to_le_bytes().to_vec(),
+    );
+    close.accounts[2].is_writable = false;
+    let table_lamports = lamports_of(&context, &lookup_table);
+    context.process_and_validate_instruction(
+        &close,
+        &[
+            Check::err(ProgramError::Immutable),
+            Check::account(&lookup_table)
+                .lamports(table_lamports)
+                .build(),
+        ],
+    );
+
+    let authority_lamports = lamports_of(&context, &authority);
+    close.accounts[2].is_writable = true;
+    context.process_and_validate_instruction(
+        &close,
+        &[
+            Check::success(),
+            Check::account(&authority)
+                .lamports(authority_lamports + table_lamports)
+                .build(),
+            Check::account(&lookup_table).lamports(0).space(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_extend_with_trailing_instruction_data_fails() {
+    let table = TestTable::with_addresses(&[Pubkey::new_unique()]);
+
+    // The address count is right, but the payload runs one byte past it.
+    let mut instruction = extend_instruction(table.key, AUTHORITY, PAYER, &[Pubkey::new_unique()]);
+    instruction.data.push(0);
+    table.context.process_and_validate_instruction(
+        &instruction,
+        &[Check::err(ProgramError::InvalidInstructionData)],
+    );
+
+    assert_eq!(table.addresses().len(), 1);
+}
+
+/// A table deactivated at `deactivation_slot`, with the clock at
+/// `current_slot` and a slot hashes account holding `slot_hashes`.
+fn deactivated_table_with_slot_hashes(
+    deactivation_slot: u64,
+    current_slot: u64,
+    slot_hashes: &[u64],
+) -> (TestTable, Pubkey) {
+    let mut table = TestTable::new();
+    table.advance_clock(current_slot);
+
+    let recipient = Pubkey::new_unique();
+    let (slot_key, slot_account) = populated_slot_hashes(slot_hashes);
+    {
+        let mut accounts = table.context.account_store.borrow_mut();
+        accounts.insert(
+            table.key,
+            make_lookup_table_account(Some(AUTHORITY), &[], deactivation_slot),
+        );
+        accounts.insert(recipient, Account::default());
+        accounts.insert(slot_key, slot_account);
+    }
+    (table, recipient)
+}
+
+/// Makes `slots` the slot hashes that instructions not passing the sysvar
+/// account read through the syscall.
+fn set_sysvar_slot_hashes(table: &mut TestTable, slots: &[u64]) {
+    let entries: Vec<_> = slots
+        .iter()
+        .map(|slot| (*slot, solana_program::hash::Hash::new_from_array([1; 32])))
+        .collect();
+    table.context.mollusk.sysvars.slot_hashes =
+        solana_program::slot_hashes::SlotHashes::new(&entries);
+}
+
+#[test]
+fn test_close_follows_shorter_slot_hashes() {
+    let current_slot = 1_000;
+    let slot_hashes: Vec<u64> = (current_slot - 8..current_slot).rev().collect();
+
+    // Fifth of eight entries: four more blocks to go, counted against the
+    // sysvar's own length rather than `SLOT_HASHES_MAX_ENTRIES`.
+    let (table, recipient) =
+        deactivated_table_with_slot_hashes(current_slot - 5, current_slot, &slot_hashes);
+    let logs = logs_of(
+        &table.context,
+        &close_instruction(table.key, AUTHORITY, recipient),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("fully deactivated in 4 blocks")));
+
+    // Just past the shorter window.
+    let (table, recipient) =
+        deactivated_table_with_slot_hashes(current_slot - 9, current_slot, &slot_hashes);
+    table.context.process_and_validate_instruction(
+        &close_instruction(table.key, AUTHORITY, recipient),
+        &[
+            Check::success(),
+            Check::account(&table.key).lamports(0).space(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_close_follows_longer_slot_hashes() {
+    // A cluster keeping more entries than `SLOT_HASHES_MAX_ENTRIES` still has
+    // the deactivation slot, so the table keeps cooling down even though
+    // more than `SLOT_HASHES_MAX_ENTRIES` slots have passed.
+    let current_slot = 1_000;
+    let slot_hashes: Vec<u64> = (current_slot - 600..current_slot).rev().collect();
+    let deactivation_slot = current_slot - 550;
+
+    let (table, recipient) =
+        deactivated_table_with_slot_hashes(deactivation_slot, current_slot, &slot_hashes);
+    let logs = logs_of(
+        &table.context,
+        &close_instruction(table.key, AUTHORITY, recipient),
+        &[
+            Check::err(ProgramError::InvalidArgument),
+            Check::account(&table.key)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+        ],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("fully deactivated in 51 blocks")));
+}
+
+#[test]
+fn test_close_waits_for_slot_hashes_across_skipped_slots() {
+    // With every other slot skipped, the slot hashes reach back twice as
+    // many slots. A table deactivated more than `SLOT_HASHES_MAX_ENTRIES`
+    // slots ago is still usable, so the clock alone can never clear a close.
+    let current_slot = 2_000;
+    let slot_hashes: Vec<u64> = (0..SLOT_HASHES_MAX_ENTRIES)
+        .map(|i| current_slot - 2 - 2 * i)
+        .collect();
+    let deactivation_slot = current_slot - 600;
+
+    let (mut table, recipient) =
+        deactivated_table_with_slot_hashes(deactivation_slot, current_slot, &slot_hashes);
+    table.context.process_and_validate_instruction(
+        &close_instruction(table.key, AUTHORITY, recipient),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+
+    // The same holds when the sysvar is read through the syscall instead.
+    set_sysvar_slot_hashes(&mut table, &slot_hashes);
+    let mut close = close_instruction(table.key, AUTHORITY, recipient);
+    close.accounts.pop();
+    table.context.process_and_validate_instruction(
+        &close,
+        &[
+            Check::err(ProgramError::InvalidArgument),
+            Check::account(&table.key)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_freeze_reports_deactivating_while_in_slot_hashes() {
+    // Past `SLOT_HASHES_MAX_ENTRIES` slots, but with skipped slots the
+    // deactivation slot is still in the slot hashes, so the table is still
+    // deactivating.
+    let current_slot = 2_000;
+    let slot_hashes: Vec<u64> = (0..SLOT_HASHES_MAX_ENTRIES)
+        .map(|i| current_slot - 2 - 2 * i)
+        .collect();
+    let (mut table, _) =
+        deactivated_table_with_slot_hashes(current_slot - 600, current_slot, &slot_hashes);
+    set_sysvar_slot_hashes(&mut table, &slot_hashes);
+
+    let logs = logs_of(
+        &table.context,
+        &freeze_instruction(table.key, AUTHORITY),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+    assert!(logs
+        .iter()
+        .any(|log| log.contains("Deactivating tables cannot be frozen")));
+}
+
+#[test]
+fn test_create_lookup_table_with_program_as_authority() {
+    let payer = Pubkey::new_unique();
+    let (context, _) = create_lookup_table(AUTHORITY, payer, 10_000_000_000);
+    context.account_store.borrow_mut().insert(
+        PROGRAM_ID,
+        program::create_program_account_loader_v3(&PROGRAM_ID),
+    );
+
+    // The program id is just another seed: the derivation succeeds, and the
+    // address can't collide with a table of any other authority because the
+    // seeds differ. Create doesn't need the authority to sign, so this is
+    // accepted, but no one can ever sign as the program id, so the table
+    // can never be extended, frozen, deactivated or closed.
+    let lookup_table = add_lookup_table(&context, PROGRAM_ID, payer);
+
+    let (expected_table, _) =
+        Pubkey::find_program_address(&[PROGRAM_ID.as_ref(), &0u64.to_le_bytes()], &PROGRAM_ID);
+    assert_eq!(lookup_table, expected_table);
+    let meta = meta_of(&context, &lookup_table);
+    assert_eq!(meta.authority, PROGRAM_ID.to_bytes());
+    assert!(!meta.is_frozen());
+    assert_eq!(addresses_len_of(&context, &lookup_table), 0);
+}
+
+#[test]
+fn test_create_lookup_table_duplicate_slot_in_sysvar() {
+    let authority = Pubkey::new_unique();
+    let recent_slot: u64 = 10;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+
+    // Only the runtime can write the sysvar, so duplicates can't occur on a
+    // real cluster. Were they there, the slot is still recent, and the table
+    // is created and paid for once.
+    let (slot_key, slot_account) = populated_slot_hashes(&[recent_slot, recent_slot, 9]);
+    let mut accounts = HashMap::from([
+        (authority, Account::default()),
+        (
+            PAYER,
+            Account {
+                lamports: 1_000_000_000,
+                ..Account::default()
+            },
+        ),
+        (lookup_table, Account::default()),
+        (slot_key, slot_account),
+    ]);
+    let (system_program, system_account) = program::keyed_account_for_system_program();
+    accounts.insert(system_program, system_account);
+
+    let mut mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    mollusk.warp_to_slot(recent_slot + 1);
+    let context = mollusk.with_context(accounts);
+    let rent_exempt_lamports = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE);
+    context.process_and_validate_instruction(
+        &create_instruction(lookup_table, authority, PAYER, recent_slot, bump),
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .owner(&PROGRAM_ID)
+                .lamports(rent_exempt_lamports)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+            Check::account(&PAYER)
+                .lamports(1_000_000_000 - rent_exempt_lamports)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_address_offset() {
+    assert_eq!(address_offset(0), Some(LOOKUP_TABLE_META_SIZE));
+    assert_eq!(address_offset(255), Some(LOOKUP_TABLE_META_SIZE + 255 * 32));
+    assert_eq!(address_offset(256), None);
+    assert_eq!(address_offset(usize::MAX), None);
+
+    let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let table = TestTable::with_addresses(&addresses);
+    let store = table.context.account_store.borrow();
+    let data = &store.get(&table.key).unwrap().data;
+    let offset = address_offset(2).unwrap();
+    assert_eq!(data[offset..offset + 32], addresses[2].to_bytes());
+}
+
+#[test]
+fn test_close_with_wrong_slot_hashes_account_fails() {
+    let authority = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let (mut context, lookup_table) = create_lookup_table(authority, PAYER, 10_000_000_000);
+    context.process_and_validate_instruction(
+        &deactivate_instruction(lookup_table, authority),
+        &[Check::success()],
+    );
+    context.mollusk.warp_to_slot(SLOT_HASHES_MAX_ENTRIES + 1);
+
+    // Sysvar-shaped data with no entries would make the table closeable, but
+    // it isn't at the sysvar's address.
+    let (_, fake_slot_hashes) = populated_slot_hashes(&[]);
+    let fake_slot_hashes_key = Pubkey::new_unique();
+    {
+        let mut accounts = context.account_store.borrow_mut();
+        accounts.insert(fake_slot_hashes_key, fake_slot_hashes);
+        accounts.insert(recipient, Account::default());
+    }
+
+    let mut close = close_instruction(lookup_table, authority, recipient);
+    close.accounts[3] = AccountMeta::new_readonly(fake_slot_hashes_key, false);
+    let table_lamports = lamports_of(&context, &lookup_table);
+    context.process_and_validate_instruction(
+        &close,
+        &[
+            Check::err(ProgramError::InvalidArgument),
+            Check::account(&lookup_table)
+                .lamports(table_lamports)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+        ],
+    );
 }
@@ -0,0 +1,31 @@
+#![no_std]
+
+use p_address_lookup_table::prelude::*;
+
+/// Active addresses of the table in `data`, if `owner` is the program.
+pub fn active_addresses<'a>(
+    owner: &Pubkey,
+    data: &'a [u8],
+    current_slot: Slot,
+) -> Result<&'a [Pubkey], ProgramError> {
+    if !check_id(owner) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let table = LookupTable::from_bytes(data)?;
+    let meta: LookupTableMeta = table.meta();
+    let addresses = table.addresses();
+    Ok(&addresses[..meta
+        .active_addresses_len(current_slot, addresses.len())
+        .min(addresses.len())])
+}
+
+/// How many more addresses `payer_lamports` can fund for a table that
+/// currently holds `count`.
+pub fn room_for(count: usize, payer_lamports: u64, rent: &Rent) -> usize {
+    p_address_lookup_table::instruction::max_addable(count, payer_lamports, rent)
+}
+
+pub fn unknown_instruction() -> ProgramError {
+    LookupTableError::UnknownInstruction.into()
+}
@@ -0,0 +1,223 @@
+//! Host-side tests calling the processor directly over fabricated accounts,
+//! covering validation branches without the SVM or the compiled program.
+
+use p_address_lookup_table::{
+    interface::{
+        AUTHORITY_OFFSET, AUTHORITY_TAG_OFFSET, DEACTIVATION_SLOT_OFFSET,
+        LOOKUP_TABLE_DISCRIMINATOR, LOOKUP_TABLE_META_SIZE,
+    },
+    processor::{process_deactivate_lookup_table, process_freeze_lookup_table},
+    state::LookupTableMeta,
+};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+const PROGRAM_ID: Pubkey = [7; 32];
+const AUTHORITY: Pubkey = [1; 32];
+const OTHER: Pubkey = [2; 32];
+
+/// Size of the runtime account header preceding the account data.
+const ACCOUNT_HEADER_SIZE: usize = 88;
+/// Marker the runtime writes for non-duplicated accounts, which pinocchio
+/// reuses as the "not borrowed" state.
+const NOT_BORROWED: u8 = u8::MAX;
+
+/// Owned, 8-byte aligned buffer laid out like an account in the runtime
+/// input region: header followed by the account data.
+struct TestAccount {
+    buffer: Vec<u64>,
+}
+
+impl TestAccount {
+    fn new(key: Pubkey, owner: Pubkey, is_signer: bool, is_writable: bool, data: &[u8]) -> Self {
+        let len = ACCOUNT_HEADER_SIZE + data.len();
+        let mut buffer = vec![0u64; len.div_ceil(8)];
+
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, buffer.len() * 8)
+        };
+        bytes[0] = NOT_BORROWED;
+        bytes[1] = is_signer as u8;
+        bytes[2] = is_writable as u8;
+        bytes[3] = 0; // executable
+        bytes[4..8].copy_from_slice(&0i32.to_le_bytes()); // resize delta
+        bytes[8..40].copy_from_slice(&key);
+        bytes[40..72].copy_from_slice(&owner);
+        bytes[72..80].copy_from_slice(&1_000_000u64.to_le_bytes());
+        bytes[80..88].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        bytes[ACCOUNT_HEADER_SIZE..len].copy_from_slice(data);
+
+        Self { buffer }
+    }
+
+    fn info(&mut self) -> AccountInfo {
+        // SAFETY: `AccountInfo` is a pointer to the account header.
+        unsafe { core::mem::transmute::<*mut u8, AccountInfo>(self.buffer.as_mut_ptr() as *mut u8) }
+    }
+
+    fn data(&self) -> &[u8] {
+        let bytes = unsafe {
+            core::slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.buffer.len() * 8)
+        };
+        let data_len = u64::from_le_bytes(bytes[80..88].try_into().unwrap()) as usize;
+        &bytes[ACCOUNT_HEADER_SIZE..ACCOUNT_HEADER_SIZE + data_len]
+    }
+}
+
+fn lookup_table_data(
+    authority: Option<Pubkey>,
+    deactivation_slot: u64,
+    addresses: usize,
+) -> Vec<u8> {
+    let mut data = vec![0; LOOKUP_TABLE_META_SIZE + addresses * 32];
+    data[0..4].copy_from_slice(&LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes());
+    data[DEACTIVATION_SLOT_OFFSET..DEACTIVATION_SLOT_OFFSET + 8]
+        .copy_from_slice(&deactivation_slot.to_le_bytes());
+    if let Some(authority) = authority {
+        data[AUTHORITY_TAG_OFFSET] = 1;
+        data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + 32].copy_from_slice(&authority);
+    }
+    for (i, address) in data[LOOKUP_TABLE_META_SIZE..]
+        .chunks_exact_mut(32)
+        .enumerate()
+    {
+        address.fill(i as u8 + 1);
+    }
+    data
+}
+
+fn meta(data: &[u8]) -> LookupTableMeta {
+    unsafe { core::ptr::read_unaligned(data[4..].as_ptr() as *const LookupTableMeta) }
+}
+
+fn freeze(lookup_table: &mut TestAccount, authority: &mut TestAccount) -> Result<(), ProgramError> {
+    process_freeze_lookup_table(&PROGRAM_ID, &[lookup_table.info(), authority.info()])
+}
+
+#[test]
+fn test_freeze_not_enough_accounts() {
+    let mut lookup_table = TestAccount::new(
+        OTHER,
+        PROGRAM_ID,
+        false,
+        true,
+        &lookup_table_data(Some(AUTHORITY), u64::MAX, 1),
+    );
+
+    assert_eq!(
+        process_freeze_lookup_table(&PROGRAM_ID, &[lookup_table.info()]),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn test_freeze_wrong_owner() {
+    let data = lookup_table_data(Some(AUTHORITY), u64::MAX, 1);
+    let mut lookup_table = TestAccount::new(OTHER, AUTHORITY, false, true, &data);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], true, false, &[]);
+
+    assert_eq!(
+        freeze(&mut lookup_table, &mut authority),
+        Err(ProgramError::InvalidAccountOwner)
+    );
+}
+
+#[test]
+fn test_freeze_authority_not_signer() {
+    let data = lookup_table_data(Some(AUTHORITY), u64::MAX, 1);
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &data);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], false, false, &[]);
+
+    assert_eq!(
+        freeze(&mut lookup_table, &mut authority),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+}
+
+#[test]
+fn test_freeze_truncated_table() {
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &[0; 20]);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], true, false, &[]);
+
+    assert_eq!(
+        freeze(&mut lookup_table, &mut authority),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn test_freeze_already_frozen() {
+    let data = lookup_table_data(None, u64::MAX, 1);
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &data);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], true, false, &[]);
+
+    assert_eq!(
+        freeze(&mut lookup_table, &mut authority),
+        Err(ProgramError::Immutable)
+    );
+}
+
+#[test]
+fn test_freeze_incorrect_authority() {
+    let data = lookup_table_data(Some(AUTHORITY), u64::MAX, 1);
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &data);
+    let mut authority = TestAccount::new(OTHER, [0; 32], true, false, &[]);
+
+    assert_eq!(
+        freeze(&mut lookup_table, &mut authority),
+        Err(ProgramError::IncorrectAuthority)
+    );
+}
+
+#[test]
+fn test_freeze_empty_table() {
+    let data = lookup_table_data(Some(AUTHORITY), u64::MAX, 0);
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &data);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], true, false, &[]);
+
+    assert_eq!(
+        freeze(&mut lookup_table, &mut authority),
+        Err(ProgramError::InvalidInstructionData)
+    );
+}
+
+#[test]
+fn test_freeze_clears_authority() {
+    let data = lookup_table_data(Some(AUTHORITY), u64::MAX, 2);
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &data);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], true, false, &[]);
+
+    assert_eq!(freeze(&mut lookup_table, &mut authority), Ok(()));
+
+    let frozen = meta(lookup_table.data());
+    assert_eq!(frozen.authority_tag, 0);
+    assert_eq!(frozen.authority, [0; 32]);
+    // Addresses are left untouched.
+    assert_eq!(
+        lookup_table.data()[LOOKUP_TABLE_META_SIZE..],
+        data[LOOKUP_TABLE_META_SIZE..]
+    );
+}
+
+#[test]
+fn test_deactivate_already_deactivated() {
+    let data = lookup_table_data(Some(AUTHORITY), 42, 1);
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &data);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], true, false, &[]);
+
+    assert_eq!(
+        process_deactivate_lookup_table(&PROGRAM_ID, &[lookup_table.info(), authority.info()], 0),
+        Err(ProgramError::InvalidArgument)
+    );
+}
+
+#[test]
+fn test_deactivate_frozen_table() {
+    let data = lookup_table_data(None, u64::MAX, 1);
+    let mut lookup_table = TestAccount::new(OTHER, PROGRAM_ID, false, true, &data);
+    let mut authority = TestAccount::new(AUTHORITY, [0; 32], true, false, &[]);
+
+    assert_eq!(
+        process_deactivate_lookup_table(&PROGRAM_ID, &[lookup_table.info(), authority.info()], 0),
+        Err(ProgramError::Immutable)
+    );
+}
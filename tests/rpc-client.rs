@@ -0,0 +1,247 @@
+//! `rpc::client` against a mocked transport replaying canned responses, so
+//! the requests it sends and the way it reads the answers are both checked
+//! without a node.
+#![cfg(feature = "rpc")]
+
+use p_address_lookup_table::interface::AUTHORITY_OFFSET;
+use p_address_lookup_table::rpc::client::{
+    await_deactivation_cooldown, fetch_lookup_table, fetch_tables_for_authority, RpcError,
+    RpcTransport,
+};
+use p_address_lookup_table::rpc::ParseError;
+use serde_json::{json, Value};
+use solana_pubkey::Pubkey;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::{ready, Future};
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+const LOOKUP_TABLE: &str = include_str!("fixtures/rpc/lookup_table.json");
+const DEACTIVATED_TABLE: &str = include_str!("fixtures/rpc/deactivated_frozen_lookup_table.json");
+const WRONG_OWNER: &str = include_str!("fixtures/rpc/wrong_owner.json");
+/// Deactivation slot of `DEACTIVATED_TABLE`.
+const DEACTIVATION_SLOT: u64 = 345_600_000;
+const SLOT_HASHES: &str = "SysvarS1otHashes111111111111111111111111111";
+
+/// Answers requests from a queue of expected methods and their results.
+#[derive(Default)]
+struct MockRpc {
+    responses: RefCell<VecDeque<(&'static str, Value)>>,
+    requests: RefCell<Vec<Value>>,
+    sleeps: Cell<usize>,
+}
+
+impl MockRpc {
+    fn respond(self, method: &'static str, result: Value) -> Self {
+        self.responses.borrow_mut().push_back((method, result));
+        self
+    }
+}
+
+impl RpcTransport for MockRpc {
+    fn request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> impl Future<Output = Result<Value, RpcError>> {
+        let (expected, result) = self
+            .responses
+            .borrow_mut()
+            .pop_front()
+            .unwrap_or_else(|| panic!("unexpected {method} request"));
+        assert_eq!(method, expected);
+        self.requests.borrow_mut().push(params);
+        ready(Ok(result))
+    }
+
+    fn sleep(&self, _duration: Duration) -> impl Future<Output = ()> {
+        self.sleeps.set(self.sleeps.get() + 1);
+        ready(())
+    }
+}
+
+/// The mock answers right away, so every future completes on first poll.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("the mock transport never waits"),
+    }
+}
+
+/// The `result` of a `getAccountInfo` fixture.
+fn result_of(fixture: &str) -> Value {
+    serde_json::from_str::<Value>(fixture).unwrap()["result"].take()
+}
+
+/// A `getAccountInfo` result for the slot hashes sysvar at `slot`, holding
+/// `slots` most recent first.
+fn slot_hashes_result(slot: u64, slots: &[u64]) -> Value {
+    let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+    for slot in slots {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(&[7; 32]);
+    }
+    json!({
+        "context": { "slot": slot },
+        "value": {
+            "data": [encode_base64(&data), "base64"],
+            "owner": "Sysvar1111111111111111111111111111111111111",
+        },
+    })
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[test]
+fn test_fetch_lookup_table() {
+    let table = Pubkey::new_unique();
+    let rpc = MockRpc::default().respond("getAccountInfo", result_of(LOOKUP_TABLE));
+
+    let fetched = block_on(fetch_lookup_table(&rpc, &table.to_bytes())).unwrap();
+
+    assert_eq!(fetched.meta.last_extended_slot, 345_678_800);
+    assert_eq!(
+        fetched.meta.authority,
+        Pubkey::from_str_const("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM").to_bytes()
+    );
+    assert_eq!(fetched.addresses.len(), 3);
+    assert_eq!(
+        rpc.requests.borrow()[0],
+        json!([table.to_string(), { "encoding": "base64" }])
+    );
+}
+
+#[test]
+fn test_fetch_lookup_table_errors() {
+    let table = Pubkey::new_unique().to_bytes();
+
+    let rpc = MockRpc::default().respond(
+        "getAccountInfo",
+        json!({ "context": { "slot": 1 }, "value": null }),
+    );
+    assert_eq!(
+        block_on(fetch_lookup_table(&rpc, &table)).unwrap_err(),
+        RpcError::AccountNotFound
+    );
+
+    let rpc = MockRpc::default().respond("getAccountInfo", result_of(WRONG_OWNER));
+    assert_eq!(
+        block_on(fetch_lookup_table(&rpc, &table)).unwrap_err(),
+        RpcError::Parse(ParseError::InvalidOwner)
+    );
+
+    let rpc = MockRpc::default().respond("getAccountInfo", json!({ "value": null }));
+    assert_eq!(
+        block_on(fetch_lookup_table(&rpc, &table)).unwrap_err(),
+        RpcError::InvalidResponse
+    );
+}
+
+#[test]
+fn test_fetch_tables_for_authority() {
+    let authority = Pubkey::from_str_const("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM");
+    let tables = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let account = result_of(LOOKUP_TABLE)["value"].take();
+    let rpc = MockRpc::default().respond(
+        "getProgramAccounts",
+        json!([
+            { "pubkey": tables[0].to_string(), "account": account },
+            { "pubkey": tables[1].to_string(), "account": account },
+        ]),
+    );
+
+    let fetched = block_on(fetch_tables_for_authority(&rpc, &authority.to_bytes())).unwrap();
+
+    assert_eq!(fetched.len(), 2);
+    for ((key, table), expected_key) in fetched.iter().zip(&tables) {
+        assert_eq!(key, &expected_key.to_bytes());
+        assert_eq!(table.meta.authority, authority.to_bytes());
+        assert_eq!(table.addresses.len(), 3);
+    }
+
+    let params = &rpc.requests.borrow()[0];
+    assert_eq!(
+        params[0],
+        Pubkey::new_from_array(p_address_lookup_table::LOOKUP_TABLE_PROGRAM_ID).to_string()
+    );
+    assert_eq!(params[1]["encoding"], "base64");
+    assert_eq!(
+        params[1]["filters"],
+        json!([
+            // The discriminator, 1 as a little endian `u32`.
+            { "memcmp": { "offset": 0, "bytes": "2UzHM" } },
+            { "memcmp": { "offset": AUTHORITY_OFFSET, "bytes": authority.to_string() } },
+        ])
+    );
+}
+
+#[test]
+fn test_await_deactivation_cooldown() {
+    let table = Pubkey::new_unique().to_bytes();
+    let recent_slots: Vec<u64> = (DEACTIVATION_SLOT - 10..DEACTIVATION_SLOT + 100)
+        .rev()
+        .collect();
+
+    // Still in the slot hashes on the first poll, gone on the second.
+    let rpc = MockRpc::default()
+        .respond("getAccountInfo", result_of(DEACTIVATED_TABLE))
+        .respond(
+            "getAccountInfo",
+            slot_hashes_result(DEACTIVATION_SLOT + 100, &recent_slots),
+        )
+        .respond("getAccountInfo", result_of(DEACTIVATED_TABLE))
+        .respond(
+            "getAccountInfo",
+            slot_hashes_result(DEACTIVATION_SLOT + 600, &[DEACTIVATION_SLOT + 599]),
+        );
+
+    block_on(await_deactivation_cooldown(
+        &rpc,
+        &table,
+        Duration::from_secs(1),
+    ))
+    .unwrap();
+
+    assert_eq!(rpc.sleeps.get(), 1);
+    assert!(rpc.responses.borrow().is_empty());
+    assert_eq!(rpc.requests.borrow()[1][0], SLOT_HASHES);
+}
+
+#[test]
+fn test_await_deactivation_cooldown_of_active_table() {
+    let table = Pubkey::new_unique().to_bytes();
+    let rpc = MockRpc::default().respond("getAccountInfo", result_of(LOOKUP_TABLE));
+
+    assert_eq!(
+        block_on(await_deactivation_cooldown(
+            &rpc,
+            &table,
+            Duration::from_secs(1)
+        ))
+        .unwrap_err(),
+        RpcError::NotDeactivated
+    );
+    assert_eq!(rpc.sleeps.get(), 0);
+}
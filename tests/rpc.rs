@@ -0,0 +1,137 @@
+//! `rpc::from_rpc_account` over `getAccountInfo` responses and
+//! `rpc::query_version` over `simulateTransaction` responses in
+//! `tests/fixtures/rpc`, laid out as mainnet RPC nodes return them.
+#![cfg(feature = "std")]
+
+use p_address_lookup_table::interface::{ProgramVersion, PROGRAM_FEATURES};
+use p_address_lookup_table::rpc::{
+    from_rpc_account, query_version, AddressLookupTableOwned, ParseError,
+};
+use serde_json::Value;
+use solana_pubkey::Pubkey;
+use std::str::FromStr;
+
+/// Owner and base64 data of the `getAccountInfo` response in `fixture`.
+fn owner_and_data(fixture: &str) -> ([u8; 32], String) {
+    let response: Value = serde_json::from_str(fixture).unwrap();
+    let value = &response["result"]["value"];
+    assert_eq!(value["data"][1], "base64");
+
+    let owner = Pubkey::from_str(value["owner"].as_str().unwrap()).unwrap();
+    (
+        owner.to_bytes(),
+        value["data"][0].as_str().unwrap().to_string(),
+    )
+}
+
+fn parse(fixture: &str) -> Result<AddressLookupTableOwned, ParseError> {
+    let (owner, data) = owner_and_data(fixture);
+    from_rpc_account(&owner, &data)
+}
+
+#[test]
+fn test_from_rpc_account_active_table() {
+    let table = parse(include_str!("fixtures/rpc/lookup_table.json")).unwrap();
+
+    assert_eq!(table.meta.deactivation_slot, u64::MAX);
+    assert_eq!(table.meta.last_extended_slot, 345_678_800);
+    assert_eq!(table.meta.last_extended_slot_start_index, 2);
+    assert_eq!(table.meta.authority_tag, 1);
+    assert_eq!(
+        table.meta.authority,
+        Pubkey::from_str_const("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM").to_bytes()
+    );
+    assert_eq!(
+        table.addresses,
+        [
+            Pubkey::from_str_const("11111111111111111111111111111111").to_bytes(),
+            Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").to_bytes(),
+            Pubkey::from_str_const("SysvarC1ock11111111111111111111111111111111").to_bytes(),
+        ]
+    );
+}
+
+#[test]
+fn test_from_rpc_account_deactivated_frozen_table() {
+    let table = parse(include_str!(
+        "fixtures/rpc/deactivated_frozen_lookup_table.json"
+    ))
+    .unwrap();
+
+    assert_eq!(table.meta.deactivation_slot, 345_600_000);
+    assert_eq!(table.meta.authority_tag, 0);
+    assert_eq!(table.meta.authority, [0; 32]);
+    assert_eq!(table.addresses, [[0; 32]]);
+}
+
+#[test]
+fn test_from_rpc_account_wrong_owner() {
+    // Valid table data, so only the owner check can reject it.
+    assert_eq!(
+        parse(include_str!("fixtures/rpc/wrong_owner.json")).unwrap_err(),
+        ParseError::InvalidOwner
+    );
+}
+
+#[test]
+fn test_from_rpc_account_truncated_data() {
+    assert_eq!(
+        parse(include_str!("fixtures/rpc/truncated_lookup_table.json")).unwrap_err(),
+        ParseError::InvalidAccountData
+    );
+}
+
+#[test]
+fn test_from_rpc_account_malformed_base64() {
+    let (owner, data) = owner_and_data(include_str!("fixtures/rpc/lookup_table.json"));
+
+    for malformed in [
+        &data[..data.len() - 1],
+        data.replace('A', "-").as_str(),
+        format!("={}", &data[1..]).as_str(),
+    ] {
+        assert_eq!(
+            from_rpc_account(&owner, malformed).unwrap_err(),
+            ParseError::InvalidBase64
+        );
+    }
+}
+
+/// Program id and base64 data of the `returnData` of the
+/// `simulateTransaction` response in `fixture`.
+fn return_data(fixture: &str) -> ([u8; 32], String) {
+    let response: Value = serde_json::from_str(fixture).unwrap();
+    let return_data = &response["result"]["value"]["returnData"];
+    assert_eq!(return_data["data"][1], "base64");
+
+    let program_id = Pubkey::from_str(return_data["programId"].as_str().unwrap()).unwrap();
+    (
+        program_id.to_bytes(),
+        return_data["data"][0].as_str().unwrap().to_string(),
+    )
+}
+
+#[test]
+fn test_query_version() {
+    let (program_id, data) = return_data(include_str!("fixtures/rpc/simulate_get_version.json"));
+
+    assert_eq!(
+        query_version(&program_id, &data).unwrap(),
+        ProgramVersion {
+            major: 1,
+            minor: 2,
+            patch: 3,
+            features: PROGRAM_FEATURES,
+        }
+    );
+
+    assert_eq!(
+        query_version(&Pubkey::new_unique().to_bytes(), &data).unwrap_err(),
+        ParseError::InvalidProgramId
+    );
+    // The return data of another instruction, here `RentStatus`.
+    assert_eq!(
+        query_version(&program_id, "AAAAAAAAAAAAAAAAAAAAAAE=").unwrap_err(),
+        ParseError::InvalidReturnData
+    );
+}
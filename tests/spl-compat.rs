@@ -0,0 +1,819 @@
+//! Instructions built with the upstream `solana-address-lookup-table-interface`
+//! builders must be accepted by this program exactly like our own encoding.
+
+mod common;
+
+use common::{
+    lookup_table_view, LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE, PROGRAM_FILE_NAME,
+    PROGRAM_ID, SLOT_HASHES_MAX_ENTRIES,
+};
+use mollusk_svm::{program, result::Check, sysvar, Mollusk, MolluskContext};
+use p_address_lookup_table::instruction::derive_lookup_table_address;
+use p_address_lookup_table::interface::{
+    CLOSE_LOOKUP_TABLE, CREATE_LOOKUP_TABLE, DEACTIVATE_LOOKUP_TABLE, DEACTIVATION_REASON_OFFSET,
+    FREEZE_LOOKUP_TABLE, LOOKUP_TABLE_DISCRIMINATOR, VERSION_OFFSET,
+};
+use p_address_lookup_table::state::{serialize_new_lookup_table, LookupTable};
+use solana_account::Account;
+use solana_address_lookup_table_interface::instruction as upstream;
+use solana_address_lookup_table_interface::state as upstream_state;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_program_error::ProgramError;
+use solana_pubkey::Pubkey;
+use solana_rent::Rent;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Re-keys an upstream instruction with this crate's pubkey type.
+fn from_upstream(instruction: Instruction) -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_from_array(instruction.program_id.to_bytes()),
+        accounts: instruction
+            .accounts
+            .iter()
+            .map(|meta| AccountMeta {
+                pubkey: Pubkey::new_from_array(meta.pubkey.to_bytes()),
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: instruction.data,
+    }
+}
+
+/// Creates a table with this program's native encoding, returning the context
+/// and the table address.
+fn create_lookup_table(
+    authority: Pubkey,
+    payer: Pubkey,
+) -> (MolluskContext<HashMap<Pubkey, Account>>, Pubkey) {
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &PROGRAM_ID,
+    );
+    let (slot_key, slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+    let (system_program, system_account) = program::keyed_account_for_system_program();
+
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        payer,
+        Account {
+            lamports: 10_000_000_000,
+            ..Account::default()
+        },
+    );
+    accounts.insert(lookup_table, Account::default());
+    accounts.insert(slot_key, slot_account);
+    accounts.insert(system_program, system_account);
+
+    let (upstream_create, upstream_table) = upstream::create_lookup_table(
+        authority.to_bytes().into(),
+        payer.to_bytes().into(),
+        recent_slot,
+    );
+    let upstream_create = from_upstream(upstream_create);
+    assert_eq!(upstream_table.to_bytes(), lookup_table.to_bytes());
+
+    // Same payload as ours, plus the slot hashes account this program reads.
+    let mut create_instruction_data = Vec::with_capacity(13);
+    create_instruction_data.extend_from_slice(&CREATE_LOOKUP_TABLE.to_le_bytes());
+    create_instruction_data.extend_from_slice(&recent_slot.to_le_bytes());
+    create_instruction_data.extend_from_slice(&[bump]);
+    assert_eq!(upstream_create.data, create_instruction_data);
+
+    let mut create = upstream_create;
+    create
+        .accounts
+        .insert(3, AccountMeta::new_readonly(slot_key, false));
+
+    let mollusk = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME);
+    let context = mollusk.with_context(accounts);
+    context.process_and_validate_instruction(&create, &[Check::success()]);
+
+    (context, lookup_table)
+}
+
+#[test]
+fn test_upstream_extend_lookup_table() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, lookup_table) = create_lookup_table(authority, payer);
+
+    let new_addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let extend = from_upstream(upstream::extend_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+        Some(payer.to_bytes().into()),
+        new_addresses.iter().map(|a| a.to_bytes().into()).collect(),
+    ));
+
+    let expected_addresses: Vec<u8> = new_addresses
+        .iter()
+        .flat_map(|address| address.to_bytes())
+        .collect();
+    let result = context.process_and_validate_instruction(&extend, &[Check::success()]);
+    assert_eq!(
+        result.get_account(&lookup_table).unwrap().data[LOOKUP_TABLE_META_SIZE..],
+        expected_addresses[..]
+    );
+}
+
+#[test]
+fn test_upstream_extend_lookup_table_with_max_addresses() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, lookup_table) = create_lookup_table(authority, payer);
+
+    let new_addresses: Vec<Pubkey> = (0..LOOKUP_TABLE_MAX_ADDRESSES)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    let extend = from_upstream(upstream::extend_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+        Some(payer.to_bytes().into()),
+        new_addresses.iter().map(|a| a.to_bytes().into()).collect(),
+    ));
+
+    context.process_and_validate_instruction(
+        &extend,
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .space(LOOKUP_TABLE_META_SIZE + LOOKUP_TABLE_MAX_ADDRESSES * 32)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_upstream_freeze_lookup_table() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, lookup_table) = create_lookup_table(authority, payer);
+
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::extend_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+            Some(payer.to_bytes().into()),
+            vec![Pubkey::new_unique().to_bytes().into()],
+        )),
+        &[Check::success()],
+    );
+
+    let freeze = from_upstream(upstream::freeze_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+    ));
+    assert_eq!(freeze.data, FREEZE_LOOKUP_TABLE.to_le_bytes());
+
+    let result = context.process_and_validate_instruction(&freeze, &[Check::success()]);
+    let frozen = lookup_table_view(result.get_account(&lookup_table).unwrap());
+    // Authority tag and authority are cleared.
+    assert_eq!(frozen.authority(), None);
+    assert_eq!(frozen.meta().authority, [0; 32]);
+}
+
+#[test]
+fn test_upstream_deactivate_and_close_lookup_table() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let (mut context, lookup_table) = create_lookup_table(authority, payer);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(recipient, Account::default());
+
+    let deactivation_slot = 10;
+    context.mollusk.warp_to_slot(deactivation_slot);
+
+    let deactivate = from_upstream(upstream::deactivate_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+    ));
+    assert_eq!(deactivate.data, DEACTIVATE_LOOKUP_TABLE.to_le_bytes());
+
+    let result = context.process_and_validate_instruction(&deactivate, &[Check::success()]);
+    assert_eq!(
+        lookup_table_view(result.get_account(&lookup_table).unwrap())
+            .meta()
+            .deactivation_slot,
+        deactivation_slot
+    );
+
+    // The upstream close has no slot hashes account, which this program
+    // accepts once the table is past the slot hashes window.
+    context
+        .mollusk
+        .warp_to_slot(deactivation_slot + SLOT_HASHES_MAX_ENTRIES + 1);
+
+    let close = from_upstream(upstream::close_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+        recipient.to_bytes().into(),
+    ));
+    assert_eq!(close.data, CLOSE_LOOKUP_TABLE.to_le_bytes());
+
+    let table_lamports = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .lamports;
+    context.process_and_validate_instruction(
+        &close,
+        &[
+            Check::success(),
+            Check::account(&recipient).lamports(table_lamports).build(),
+            Check::account(&lookup_table).lamports(0).space(0).build(),
+        ],
+    );
+}
+
+const SERIALIZED_AUTHORITY: Pubkey =
+    Pubkey::from_str_const("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM");
+const ACTIVE_TABLE: &[u8] = include_bytes!("fixtures/upstream-serialized/active_lookup_table.bin");
+const FROZEN_TABLE: &[u8] = include_bytes!("fixtures/upstream-serialized/frozen_lookup_table.bin");
+const DEACTIVATED_TABLE: &[u8] =
+    include_bytes!("fixtures/upstream-serialized/deactivated_lookup_table.bin");
+
+/// A rent-exempt table account holding `data` written by the upstream
+/// interface's serializer, so the byte layout (authority `Option` encoding,
+/// padding) is the one the native program writes. The tables themselves are
+/// made up, not captured from a cluster.
+fn serialized_account(data: &[u8]) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data: data.to_vec(),
+        owner: PROGRAM_ID,
+        ..Account::default()
+    }
+}
+
+#[test]
+fn test_upstream_serialized_tables_decode() {
+    let system = Pubkey::from_str_const("11111111111111111111111111111111");
+    let token = Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+    let cases = [
+        (
+            ACTIVE_TABLE,
+            u64::MAX,
+            345_678_800,
+            2,
+            Some(SERIALIZED_AUTHORITY),
+            vec![
+                system,
+                token,
+                Pubkey::from_str_const("SysvarC1ock11111111111111111111111111111111"),
+                Pubkey::from_str_const("ComputeBudget111111111111111111111111111111"),
+            ],
+        ),
+        (
+            FROZEN_TABLE,
+            u64::MAX,
+            300_000_000,
+            0,
+            None,
+            vec![system, token],
+        ),
+        (
+            DEACTIVATED_TABLE,
+            345_000_000,
+            344_999_000,
+            1,
+            Some(SERIALIZED_AUTHORITY),
+            vec![
+                system,
+                Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"),
+            ],
+        ),
+    ];
+
+    for (data, deactivation_slot, last_extended_slot, start_index, authority, addresses) in cases {
+        let table = LookupTable::from_bytes(data).unwrap();
+        let meta = table.meta();
+        assert_eq!(meta.deactivation_slot, deactivation_slot);
+        assert_eq!(meta.is_deactivated(), deactivation_slot != u64::MAX);
+        assert_eq!(meta.last_extended_slot, last_extended_slot);
+        assert_eq!(meta.last_extended_slot_start_index, start_index);
+        assert_eq!(meta.is_frozen(), authority.is_none());
+        assert_eq!(
+            meta.authority,
+            authority.map(|a| a.to_bytes()).unwrap_or_default()
+        );
+        assert_eq!(meta.version, 0);
+        assert_eq!(meta.deactivation_reason, 0);
+        assert_eq!(
+            table.addresses(),
+            addresses.iter().map(Pubkey::to_bytes).collect::<Vec<_>>()
+        );
+
+        // The upstream parser agrees.
+        let upstream = upstream_state::AddressLookupTable::deserialize(data).unwrap();
+        assert_eq!(upstream.meta.deactivation_slot, deactivation_slot);
+        assert_eq!(
+            upstream.meta.authority.map(|a| a.to_bytes()),
+            authority.map(|a| a.to_bytes())
+        );
+        assert_eq!(upstream.addresses.len(), addresses.len());
+    }
+}
+
+fn native_table_context(
+    lookup_table: Pubkey,
+    table: Account,
+    authority: Pubkey,
+    payer: Pubkey,
+) -> MolluskContext<HashMap<Pubkey, Account>> {
+    let (slot_key, slot_account) =
+        sysvar::Sysvars::default().keyed_account_for_slot_hashes_sysvar();
+    let (system_program, system_account) = program::keyed_account_for_system_program();
+
+    let mut accounts = HashMap::new();
+    accounts.insert(lookup_table, table);
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        payer,
+        Account {
+            lamports: 10_000_000_000,
+            ..Account::default()
+        },
+    );
+    accounts.insert(slot_key, slot_account);
+    accounts.insert(system_program, system_account);
+
+    Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME).with_context(accounts)
+}
+
+#[test]
+fn test_upstream_serialized_tables_are_handled_like_native() {
+    let authority = SERIALIZED_AUTHORITY;
+    let payer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+
+    let extend = |lookup_table: Pubkey| {
+        from_upstream(upstream::extend_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+            Some(payer.to_bytes().into()),
+            vec![Pubkey::new_unique().to_bytes().into()],
+        ))
+    };
+    let freeze = |lookup_table: Pubkey| {
+        from_upstream(upstream::freeze_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+        ))
+    };
+    let close = |lookup_table: Pubkey| {
+        from_upstream(upstream::close_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+            recipient.to_bytes().into(),
+        ))
+    };
+
+    // Active: extend and freeze both go through.
+    let lookup_table = Pubkey::new_unique();
+    let context = native_table_context(
+        lookup_table,
+        serialized_account(ACTIVE_TABLE),
+        authority,
+        payer,
+    );
+    context.process_and_validate_instruction(&extend(lookup_table), &[Check::success()]);
+    context.process_and_validate_instruction(&freeze(lookup_table), &[Check::success()]);
+
+    // Frozen: the `None` authority is read as frozen.
+    let lookup_table = Pubkey::new_unique();
+    let context = native_table_context(
+        lookup_table,
+        serialized_account(FROZEN_TABLE),
+        authority,
+        payer,
+    );
+    context.process_and_validate_instruction(
+        &extend(lookup_table),
+        &[Check::err(ProgramError::Immutable)],
+    );
+    context.process_and_validate_instruction(
+        &freeze(lookup_table),
+        &[Check::err(ProgramError::Immutable)],
+    );
+
+    // Deactivated long ago: extend is refused, close succeeds.
+    let lookup_table = Pubkey::new_unique();
+    let mut context = native_table_context(
+        lookup_table,
+        serialized_account(DEACTIVATED_TABLE),
+        authority,
+        payer,
+    );
+    context
+        .mollusk
+        .warp_to_slot(345_000_000 + SLOT_HASHES_MAX_ENTRIES + 1);
+    context
+        .account_store
+        .borrow_mut()
+        .insert(recipient, Account::default());
+    context.process_and_validate_instruction(
+        &extend(lookup_table),
+        &[Check::err(ProgramError::InvalidArgument)],
+    );
+    context.process_and_validate_instruction(
+        &close(lookup_table),
+        &[
+            Check::success(),
+            Check::account(&lookup_table).lamports(0).space(0).build(),
+        ],
+    );
+}
+
+#[test]
+fn test_upstream_create_lookup_table_without_slot_hashes_account() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recent_slot: u64 = 0;
+    let (system_program, system_account) = program::keyed_account_for_system_program();
+
+    let (create, lookup_table) = upstream::create_lookup_table(
+        authority.to_bytes().into(),
+        payer.to_bytes().into(),
+        recent_slot,
+    );
+    let lookup_table = Pubkey::new_from_array(lookup_table.to_bytes());
+
+    let mut accounts = HashMap::new();
+    accounts.insert(authority, Account::default());
+    accounts.insert(
+        payer,
+        Account {
+            lamports: 10_000_000_000,
+            ..Account::default()
+        },
+    );
+    accounts.insert(lookup_table, Account::default());
+    accounts.insert(system_program, system_account);
+
+    // The exact native shape: the slot hashes are read through the syscall.
+    let context = Mollusk::new(&PROGRAM_ID, PROGRAM_FILE_NAME).with_context(accounts);
+    context.process_and_validate_instruction(
+        &from_upstream(create),
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .owner(&PROGRAM_ID)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_upstream_extend_lookup_table_without_payer() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, lookup_table) = create_lookup_table(authority, payer);
+
+    let extend = from_upstream(upstream::extend_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+        None,
+        vec![Pubkey::new_unique().to_bytes().into()],
+    ));
+    assert_eq!(extend.accounts.len(), 2);
+
+    // Nobody pays for the larger table.
+    context.process_and_validate_instruction(
+        &extend,
+        &[Check::err(ProgramError::NotEnoughAccountKeys)],
+    );
+
+    // Pre-funded, the payer is not needed.
+    context
+        .account_store
+        .borrow_mut()
+        .get_mut(&lookup_table)
+        .unwrap()
+        .lamports = Rent::default().minimum_balance(LOOKUP_TABLE_META_SIZE + 32);
+    context.process_and_validate_instruction(
+        &extend,
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .space(LOOKUP_TABLE_META_SIZE + 32)
+                .build(),
+        ],
+    );
+}
+
+#[test]
+fn test_program_id_matches_upstream() {
+    assert_eq!(
+        p_address_lookup_table::LOOKUP_TABLE_PROGRAM_ID,
+        solana_address_lookup_table_interface::program::ID.to_bytes()
+    );
+}
+
+#[test]
+fn test_program_written_tables_deserialize_with_upstream_layout() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (mut context, lookup_table) = create_lookup_table(authority, payer);
+
+    let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    context.mollusk.warp_to_slot(7);
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::extend_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+            Some(payer.to_bytes().into()),
+            addresses.iter().map(|a| a.to_bytes().into()).collect(),
+        )),
+        &[Check::success()],
+    );
+
+    let read_upstream = |context: &MolluskContext<HashMap<Pubkey, Account>>| {
+        let data = context
+            .account_store
+            .borrow()
+            .get(&lookup_table)
+            .unwrap()
+            .data
+            .clone();
+        let table = upstream_state::AddressLookupTable::deserialize(&data).unwrap();
+        (
+            table.meta.deactivation_slot,
+            table.meta.last_extended_slot,
+            table.meta.last_extended_slot_start_index,
+            table.meta.authority.map(|a| a.to_bytes()),
+            table
+                .addresses
+                .iter()
+                .map(|a| Pubkey::new_from_array(a.to_bytes()))
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    assert_eq!(
+        read_upstream(&context),
+        (
+            u64::MAX,
+            7,
+            0,
+            Some(authority.to_bytes()),
+            addresses.clone()
+        )
+    );
+
+    context.mollusk.warp_to_slot(9);
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::deactivate_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+        )),
+        &[Check::success()],
+    );
+    assert_eq!(
+        read_upstream(&context),
+        (9, 7, 0, Some(authority.to_bytes()), addresses.clone())
+    );
+}
+
+#[test]
+fn test_program_written_frozen_table_deserializes_without_authority() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (context, lookup_table) = create_lookup_table(authority, payer);
+
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::extend_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+            Some(payer.to_bytes().into()),
+            vec![Pubkey::new_unique().to_bytes().into()],
+        )),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::freeze_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+        )),
+        &[Check::success()],
+    );
+
+    let data = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .data
+        .clone();
+    let table = upstream_state::AddressLookupTable::deserialize(&data).unwrap();
+    assert_eq!(table.meta.authority, None);
+    assert_eq!(table.addresses.len(), 1);
+}
+
+#[test]
+fn test_upstream_serialized_meta_reads_field_for_field() {
+    let authority = Pubkey::new_unique();
+    let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let table = upstream_state::AddressLookupTable {
+        meta: upstream_state::LookupTableMeta {
+            deactivation_slot: 0x0102_0304_0506_0708,
+            last_extended_slot: 0x1112_1314_1516_1718,
+            last_extended_slot_start_index: 0x21,
+            authority: Some(authority.to_bytes().into()),
+            ..upstream_state::LookupTableMeta::default()
+        },
+        addresses: Cow::Owned(addresses.iter().map(|a| a.to_bytes().into()).collect()),
+    };
+    let data = table.serialize_for_tests().unwrap();
+    assert_eq!(data.len(), LOOKUP_TABLE_META_SIZE + 2 * 32);
+
+    let meta = unsafe {
+        core::ptr::read_unaligned(
+            data[4..].as_ptr() as *const p_address_lookup_table::state::LookupTableMeta
+        )
+    };
+    assert_eq!(data[0..4], LOOKUP_TABLE_DISCRIMINATOR.to_le_bytes());
+    assert_eq!(meta.deactivation_slot, 0x0102_0304_0506_0708);
+    assert_eq!(meta.last_extended_slot, 0x1112_1314_1516_1718);
+    assert_eq!(meta.last_extended_slot_start_index, 0x21);
+    assert_eq!(meta.authority_tag, 1);
+    assert_eq!(meta.authority, authority.to_bytes());
+    assert_eq!(meta.version, 0);
+    assert_eq!(meta.deactivation_reason, 0);
+    assert_eq!(
+        data[LOOKUP_TABLE_META_SIZE..],
+        [addresses[0].to_bytes(), addresses[1].to_bytes()].concat()
+    );
+}
+
+#[test]
+fn test_public_items_match_upstream_interface() {
+    assert_eq!(
+        LOOKUP_TABLE_MAX_ADDRESSES,
+        upstream_state::LOOKUP_TABLE_MAX_ADDRESSES
+    );
+    assert_eq!(
+        LOOKUP_TABLE_META_SIZE,
+        upstream_state::LOOKUP_TABLE_META_SIZE
+    );
+
+    let upstream_id = solana_address_lookup_table_interface::program::id().to_bytes();
+    assert_eq!(p_address_lookup_table::id(), upstream_id);
+    assert_eq!(p_address_lookup_table::ID, upstream_id);
+    assert!(p_address_lookup_table::check_id(&upstream_id));
+
+    let authority = Pubkey::new_unique();
+    let recent_slot = 42;
+    let (upstream_address, bump) =
+        upstream::derive_lookup_table_address(&authority.to_bytes().into(), recent_slot);
+    assert_eq!(
+        derive_lookup_table_address(
+            &authority.to_bytes(),
+            recent_slot,
+            bump,
+            &p_address_lookup_table::id()
+        ),
+        upstream_address.to_bytes()
+    );
+}
+
+/// Serializes a table holding the given fields with the upstream code.
+fn upstream_bytes(
+    deactivation_slot: u64,
+    last_extended_slot: u64,
+    last_extended_slot_start_index: u8,
+    authority: Option<Pubkey>,
+    addresses: &[Pubkey],
+) -> Vec<u8> {
+    upstream_state::AddressLookupTable {
+        meta: upstream_state::LookupTableMeta {
+            deactivation_slot,
+            last_extended_slot,
+            last_extended_slot_start_index,
+            authority: authority.map(|a| a.to_bytes().into()),
+            ..upstream_state::LookupTableMeta::default()
+        },
+        addresses: Cow::Owned(addresses.iter().map(|a| a.to_bytes().into()).collect()),
+    }
+    .serialize_for_tests()
+    .unwrap()
+}
+
+#[test]
+fn test_new_table_matches_upstream_bytes() {
+    let authority = Pubkey::new_unique();
+    let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let mut data = vec![0; LOOKUP_TABLE_META_SIZE];
+    serialize_new_lookup_table(&mut data, &authority.to_bytes()).unwrap();
+    data.extend(addresses.iter().flat_map(|a| a.to_bytes()));
+
+    assert_eq!(
+        data,
+        upstream_bytes(u64::MAX, 0, 0, Some(authority), &addresses)
+    );
+}
+
+/// Every byte of a table this program wrote matches the upstream
+/// serialization of the same fields, except the version and deactivation
+/// reason. Those live in the two bytes upstream writes as zero padding and
+/// never reads, so the upstream parser and the runtime are unaffected.
+#[test]
+fn test_program_written_table_matches_upstream_bytes() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (mut context, lookup_table) = create_lookup_table(authority, payer);
+
+    let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    context.mollusk.warp_to_slot(7);
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::extend_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+            Some(payer.to_bytes().into()),
+            addresses.iter().map(|a| a.to_bytes().into()).collect(),
+        )),
+        &[Check::success()],
+    );
+
+    let reason = 3;
+    let mut deactivate = from_upstream(upstream::deactivate_lookup_table(
+        lookup_table.to_bytes().into(),
+        authority.to_bytes().into(),
+    ));
+    deactivate.data.push(reason);
+    context.mollusk.warp_to_slot(9);
+    context.process_and_validate_instruction(&deactivate, &[Check::success()]);
+
+    let data = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .data
+        .clone();
+    let upstream = upstream_bytes(9, 7, 0, Some(authority), &addresses);
+
+    assert_eq!(data.len(), upstream.len());
+    assert_eq!(data[..VERSION_OFFSET], upstream[..VERSION_OFFSET]);
+    assert_eq!(
+        data[LOOKUP_TABLE_META_SIZE..],
+        upstream[LOOKUP_TABLE_META_SIZE..]
+    );
+    assert_eq!(upstream[VERSION_OFFSET..LOOKUP_TABLE_META_SIZE], [0, 0]);
+    // Bumped by the extend and the deactivation.
+    assert_eq!(data[VERSION_OFFSET], 2);
+    assert_eq!(data[DEACTIVATION_REASON_OFFSET], reason);
+}
+
+#[test]
+fn test_program_written_frozen_table_matches_upstream_bytes() {
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let (mut context, lookup_table) = create_lookup_table(authority, payer);
+
+    let address = Pubkey::new_unique();
+    context.mollusk.warp_to_slot(5);
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::extend_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+            Some(payer.to_bytes().into()),
+            vec![address.to_bytes().into()],
+        )),
+        &[Check::success()],
+    );
+    context.process_and_validate_instruction(
+        &from_upstream(upstream::freeze_lookup_table(
+            lookup_table.to_bytes().into(),
+            authority.to_bytes().into(),
+        )),
+        &[Check::success()],
+    );
+
+    let mut data = context
+        .account_store
+        .borrow()
+        .get(&lookup_table)
+        .unwrap()
+        .data
+        .clone();
+    // Bumped by the extend and the freeze; the only byte that differs.
+    assert_eq!(data[VERSION_OFFSET], 2);
+    data[VERSION_OFFSET] = 0;
+
+    // Upstream writes a `None` authority as the tag alone and leaves the 32
+    // bytes after it zeroed, as freezing does here.
+    assert_eq!(data, upstream_bytes(u64::MAX, 5, 0, None, &[address]));
+}
@@ -0,0 +1,68 @@
+//! Uses `testing::mollusk_add_program` the way another crate's test suite
+//! would: nothing but the public helper and a stock `Mollusk`.
+#![cfg(feature = "mollusk")]
+
+use mollusk_svm::{program, result::Check, Mollusk};
+use p_address_lookup_table::{
+    interface::{CREATE_LOOKUP_TABLE, LOOKUP_TABLE_META_SIZE},
+    testing::mollusk_add_program,
+    LOOKUP_TABLE_PROGRAM_ID,
+};
+use solana_account::Account;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+#[test]
+fn test_mollusk_add_program_deploys_at_canonical_address() {
+    let mut mollusk = Mollusk::default();
+    mollusk_add_program(&mut mollusk);
+
+    let program_id = Pubkey::from_str_const("AddressLookupTab1e1111111111111111111111111");
+    assert_eq!(program_id.to_bytes(), LOOKUP_TABLE_PROGRAM_ID);
+
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let recent_slot: u64 = 0;
+    let (lookup_table, bump) = Pubkey::find_program_address(
+        &[authority.as_ref(), &recent_slot.to_le_bytes()],
+        &program_id,
+    );
+    let (system_program, system_account) = program::keyed_account_for_system_program();
+
+    let mut data = CREATE_LOOKUP_TABLE.to_le_bytes().to_vec();
+    data.extend_from_slice(&recent_slot.to_le_bytes());
+    data.push(bump);
+    let create = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lookup_table, false),
+            AccountMeta::new_readonly(authority, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+        data,
+    };
+
+    mollusk.process_and_validate_instruction(
+        &create,
+        &[
+            (lookup_table, Account::default()),
+            (authority, Account::default()),
+            (
+                payer,
+                Account {
+                    lamports: 10_000_000_000,
+                    ..Account::default()
+                },
+            ),
+            (system_program, system_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&lookup_table)
+                .owner(&program_id)
+                .space(LOOKUP_TABLE_META_SIZE)
+                .build(),
+        ],
+    );
+}